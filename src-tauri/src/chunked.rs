@@ -0,0 +1,198 @@
+// Files transcribed a chunk at a time, so memory stays flat however long they are.
+// Chunks end in a pause and the next one starts a little before it; whatever Whisper
+// hears twice in that overlap is dropped.
+
+use std::sync::atomic::AtomicBool;
+use whisper_rs::{FullParams, WhisperContext};
+use crate::commands::language::{self, DetectedLanguage, LanguageOptions};
+use crate::dsp::{self, preprocess::{self, PreprocessOptions}, vad, SampleStream};
+use crate::inference;
+use crate::postprocess::{self, filter::FilterRules};
+use crate::transcript::{self, FilteredSegment, TranscriptSegment};
+
+/// Longest piece of audio handed to Whisper at once
+const CHUNK_MS: u64 = 120_000;
+/// A chunk ends in the longest pause within its last this many ms
+const SEARCH_MS: u64 = 10_000;
+/// Audio before a cut that the next chunk hears again, so a word cut short is heard whole once
+const OVERLAP_MS: u64 = 1_500;
+/// Most words looked at when matching the start of a chunk against the end of the last one
+const MAX_REPEAT_WORDS: usize = 8;
+
+pub(crate) struct ChunkOptions<'a> {
+    pub language: &'a LanguageOptions,
+    pub preprocess: &'a PreprocessOptions,
+    pub prompt: Option<&'a str>,
+    pub filter: &'a FilterRules,
+    pub threads: usize,
+    /// Read word timings; the params must enable token timestamps
+    pub words: bool,
+    /// File time of the stream's first sample, when only part of the file is read
+    pub start_ms: u64,
+}
+
+/// Segments of everything read, in file time
+pub(crate) struct Chunked {
+    pub segments: Vec<TranscriptSegment>,
+    pub filtered: Vec<FilteredSegment>,
+    pub language: DetectedLanguage,
+}
+
+fn word_key(word: &str) -> String {
+    word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// How many leading words of `next` repeat the last words of `previous`, ignoring case and
+/// punctuation. A single word only counts when it is too long to be a plain "I" or "the".
+fn repeated_words(previous: &str, next: &str) -> usize {
+    let previous: Vec<String> = previous.split_whitespace().map(word_key).collect();
+    let next: Vec<String> = next.split_whitespace().take(MAX_REPEAT_WORDS).map(word_key).collect();
+    (1..=previous.len().min(next.len()))
+        .rev()
+        .find(|&n| previous[previous.len() - n..] == next[..n] && (n > 1 || next[0].chars().count() >= 4))
+        .unwrap_or(0)
+}
+
+fn drop_leading_words(segment: &mut TranscriptSegment, n: usize) {
+    segment.text = segment.text.split_whitespace().skip(n).collect::<Vec<_>>().join(" ");
+    if !segment.words.is_empty() {
+        segment.words.drain(..n.min(segment.words.len()));
+        if let Some(first) = segment.words.first() {
+            segment.start_ms = first.start_ms;
+        }
+    }
+}
+
+fn shift(segment: &mut TranscriptSegment, by_ms: u64) {
+    segment.start_ms += by_ms;
+    segment.end_ms += by_ms;
+    for word in &mut segment.words {
+        word.start_ms += by_ms;
+        word.end_ms += by_ms;
+    }
+}
+
+/// Transcribe everything `stream` yields. `params` makes the decoding parameters of each
+/// chunk; language and prompt are set here, the language detected once on the first chunk.
+/// Each chunk's kept segments go to `on_segments` as soon as it is done, and `progress`
+/// gets the ms of the stream transcribed so far.
+pub(crate) fn transcribe_stream(
+    ctx: &WhisperContext,
+    stream: &mut dyn SampleStream,
+    options: &ChunkOptions,
+    cancel: &AtomicBool,
+    params: impl Fn() -> FullParams<'static, 'static>,
+    mut progress: impl FnMut(u64),
+    mut on_segments: impl FnMut(&[TranscriptSegment]) -> Result<(), String>,
+) -> Result<Chunked, String> {
+    let chunk_len = dsp::ms_to_samples(CHUNK_MS);
+    let search_len = dsp::ms_to_samples(SEARCH_MS);
+    let overlap_len = dsp::ms_to_samples(OVERLAP_MS);
+
+    let mut state = ctx.create_state().map_err(|e| format!("Failed to create state: {}", e))?;
+    let mut buffer: Vec<f32> = Vec::with_capacity(chunk_len);
+    // File position of `buffer[0]`, and how much of the buffer the last chunk already had
+    let mut offset = 0usize;
+    let mut carried = 0usize;
+    let mut language = None;
+    let mut segments: Vec<TranscriptSegment> = Vec::new();
+    let mut filtered = Vec::new();
+
+    loop {
+        let mut ended = false;
+        while buffer.len() < chunk_len && !ended {
+            ended = stream.read(chunk_len - buffer.len(), &mut buffer)? == 0;
+        }
+        if buffer.len() <= carried {
+            break;
+        }
+        let cut = if ended {
+            buffer.len()
+        } else {
+            let from = buffer.len() - search_len;
+            from + vad::pause_midpoint(&buffer[from..])
+        };
+
+        let mut samples = buffer[..cut].to_vec();
+        preprocess::process(&mut samples, options.preprocess);
+        let detected = match language {
+            Some(detected) => detected,
+            None => {
+                let detected = language::resolve(&mut state, &samples, options.language, options.threads)?;
+                println!("[Chunked] Language: {} ({:?})", detected.code, detected.probability);
+                *language.insert(detected)
+            }
+        };
+
+        let mut chunk_params = params();
+        chunk_params.set_language(Some(detected.code));
+        if let Some(prompt) = options.prompt {
+            chunk_params.set_initial_prompt(prompt);
+        }
+        let start_ms = dsp::samples_to_ms(offset);
+        let chunk_ms = dsp::samples_to_ms(cut);
+        inference::full_with_progress(&mut state, chunk_params, &samples, cancel, |percent| {
+            progress(start_ms + chunk_ms * percent.clamp(0, 100) as u64 / 100);
+        })?;
+
+        let mut chunk = transcript::read_segments(ctx, &state, options.words)?;
+        for segment in &mut chunk {
+            shift(segment, options.start_ms + start_ms);
+        }
+        // The previous chunk already transcribed the overlap
+        let covered_ms = options.start_ms + dsp::samples_to_ms(offset + carried);
+        chunk.retain(|s| (s.start_ms + s.end_ms) / 2 >= covered_ms);
+        let (mut kept, dropped) = postprocess::filter_segments(options.filter, chunk);
+
+        // A segment straddling the cut can still start with the end of the last one
+        let repeated = match (segments.last(), kept.first()) {
+            (Some(last), Some(first)) => repeated_words(&last.text, &first.text),
+            _ => 0,
+        };
+        if repeated > 0 {
+            drop_leading_words(&mut kept[0], repeated);
+            if kept[0].text.is_empty() {
+                kept.remove(0);
+            }
+        }
+        println!("[Chunked] {} segments up to {} ms", kept.len(), options.start_ms + start_ms + chunk_ms);
+
+        on_segments(&kept)?;
+        segments.extend(kept);
+        filtered.extend(dropped);
+        progress(start_ms + chunk_ms);
+        if ended {
+            break;
+        }
+
+        let keep_from = cut - overlap_len;
+        buffer.drain(..keep_from);
+        offset += keep_from;
+        carried = overlap_len;
+    }
+
+    let language = language.ok_or("No audio to transcribe")?;
+    Ok(Chunked { segments, filtered, language })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_words_ignore_case_and_punctuation() {
+        assert_eq!(repeated_words("and then we went home.", "Went home, and slept"), 2);
+        assert_eq!(repeated_words("we said hello", "hello again"), 1);
+    }
+
+    #[test]
+    fn short_single_words_are_not_repeats() {
+        assert_eq!(repeated_words("what did I", "I think so"), 0);
+        assert_eq!(repeated_words("one two", "three four"), 0);
+    }
+
+    #[test]
+    fn longest_repeat_wins() {
+        assert_eq!(repeated_words("go on and on and on", "and on and on we went"), 4);
+    }
+}
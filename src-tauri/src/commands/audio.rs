@@ -1,104 +1,661 @@
 use tauri::{State, Emitter, Manager};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use crate::state::AudioState;
+use serde::{Deserialize, Serialize};
+use crate::state::{AudioState, CaptureDevice, CaptureSource, DualCapture, LoopbackMode, PauseSpan, WarmMic};
+use crate::dsp::{self, resample::Resampler, vad::EnergyVad, WHISPER_SAMPLE_RATE};
+use crate::commands::live::LiveOptions;
+use crate::commands::loopback::{self, LoopbackOptions};
+use crate::commands::decoding::Decoding;
+use crate::commands::language::LanguageOptions;
+use crate::transcript::Transcript;
+use crate::dsp::preprocess::PreprocessOptions;
 
-#[tauri::command]
-pub async fn start_recording(
-    state: State<'_, AudioState>,
-    app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let mut is_rec = state.is_recording.lock().unwrap();
-    if *is_rec {
-        return Err("Already recording".into());
-    }
+#[derive(Clone, Serialize)]
+pub struct InputConfigInfo {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
 
-    if let Ok(mut amp) = state.max_amplitude.lock() {
-        *amp = 0.0;
+#[derive(Clone, Serialize)]
+pub struct InputDeviceInfo {
+    /// Stable identifier (`<host>:<device name>`) to pass back to `start_recording`
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    /// Captures what the speakers play rather than a microphone
+    pub is_loopback: bool,
+    pub configs: Vec<InputConfigInfo>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DeviceFallback {
+    pub requested: String,
+    pub fallback: String,
+}
+
+/// Hands-free dictation: stop after trailing silence and transcribe without the frontend
+#[derive(Clone, Deserialize)]
+pub struct AutoStopOptions {
+    /// Silence after speech before the take is stopped
+    pub hang_ms: u64,
+    /// RMS level counted as speech, VAD default if unset
+    pub threshold: Option<f32>,
+    pub model: String,
+    pub use_gpu: bool,
+    pub translate: bool,
+    #[serde(default)]
+    pub preprocess: PreprocessOptions,
+    #[serde(default)]
+    pub language: LanguageOptions,
+    #[serde(default)]
+    pub decoding: Decoding,
+    /// Interpret spoken punctuation and edit commands
+    #[serde(default)]
+    pub spoken_commands: bool,
+    #[serde(default)]
+    pub vocabulary_profile: Option<String>,
+}
+
+/// What `stop_recording` knows about the take, so nobody has to reopen the file
+#[derive(Clone, Serialize)]
+pub struct RecordingResult {
+    /// Archive WAV, if the take was saved to disk
+    pub path: Option<String>,
+    /// Length of the captured audio, pre-roll included and pauses excluded
+    pub duration_ms: u64,
+    pub pauses: Vec<PauseSpan>,
+    /// Main input; system audio when that was the only source
+    pub device: Option<CaptureDevice>,
+    /// System audio recorded alongside the microphone
+    pub system_device: Option<CaptureDevice>,
+    /// Before downmixing, 0.0–1.0
+    pub peak: f32,
+    /// Of the 16kHz take
+    pub rms: f32,
+    /// Share of 30ms frames the VAD counted as speech
+    pub speech_ratio: f32,
+    /// Peak per slice, `WAVEFORM_POINTS` slices over the whole take
+    pub waveform: Vec<f32>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AutoStopped {
+    pub reason: String,
+    pub path: Option<String>,
+    pub recording: Option<RecordingResult>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AutoTranscription {
+    pub text: Option<String>,
+    /// Not available for live sessions
+    pub transcript: Option<Transcript>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RecordingLimit {
+    pub max_secs: u64,
+    /// Which constraint set the cap: "model" or "memory"
+    pub limited_by: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RecordingLimitWarning {
+    pub remaining_secs: u64,
+    pub max_secs: u64,
+}
+
+/// Resolution of the waveform returned with each take
+const WAVEFORM_POINTS: usize = 200;
+
+/// How long before the cap the "nearly at limit" warning goes out
+const LIMIT_WARNING_SECS: u64 = 30;
+
+/// 16 kHz mono f32 held in the take buffer, plus the copy Whisper's mel
+/// spectrogram and decoder state make of it, with headroom.
+const BYTES_PER_RECORDED_SEC: u64 = WHISPER_SAMPLE_RATE as u64 * 4 * 4;
+
+/// Longest take each model turns around in reasonable time on a typical CPU,
+/// and roughly what the loaded model itself occupies.
+fn model_budget(model: &str) -> (u64, u64) {
+    const MB: u64 = 1024 * 1024;
+    match model.to_lowercase().as_str() {
+        "tiny" => (90 * 60, 300 * MB),
+        "base" => (60 * 60, 400 * MB),
+        "small" => (30 * 60, 900 * MB),
+        "medium" => (20 * 60, 2200 * MB),
+        _ => (10 * 60, 4000 * MB),
     }
-    if let Ok(mut lvl) = state.current_level.lock() {
-        *lvl = 0.0;
+}
+
+/// Cap a take by the model's practical length and by what fits in half the
+/// memory left after loading the model. Without memory info only the model counts.
+pub(crate) fn recording_limit(model: &str, available_memory: Option<u64>) -> RecordingLimit {
+    let (model_secs, model_bytes) = model_budget(model);
+    let available_memory = match available_memory {
+        Some(bytes) => bytes,
+        None => return RecordingLimit { max_secs: model_secs, limited_by: "model".into() },
+    };
+    let memory_secs = available_memory.saturating_sub(model_bytes) / 2 / BYTES_PER_RECORDED_SEC;
+    // Never go below a minute, even on a starved machine
+    let memory_secs = memory_secs.max(60);
+
+    if memory_secs < model_secs {
+        RecordingLimit { max_secs: memory_secs, limited_by: "memory".into() }
+    } else {
+        RecordingLimit { max_secs: model_secs, limited_by: "model".into() }
     }
+}
 
-    let max_amp = state.max_amplitude.clone();
-    let current_level = state.current_level.clone();
+/// cpal has no persistent device IDs, so host name + device name is the most
+/// stable thing we can hand to the frontend to save in settings.
+fn device_id(host: &cpal::Host, name: &str) -> String {
+    format!("{}:{}", host.id().name(), name)
+}
+
+pub(crate) fn find_input_device(host: &cpal::Host, wanted: &str) -> Option<cpal::Device> {
+    host.input_devices()
+        .ok()?
+        .find(|device| device.name().map(|name| device_id(host, &name) == wanted).unwrap_or(false))
+}
+
+/// Find the saved input device, falling back to the host default when it is gone.
+/// Returns the device and whether the fallback was taken.
+fn resolve_input_device(host: &cpal::Host, requested: Option<&str>) -> Result<(cpal::Device, bool), String> {
+    if let Some(device) = requested.and_then(|wanted| find_input_device(host, wanted)) {
+        return Ok((device, false));
+    }
 
-    let host = cpal::default_host();
     let device = host
         .default_input_device()
         .ok_or("No input device available")?;
+    Ok((device, requested.is_some()))
+}
+
+/// Open an input stream for any cpal sample type and hand the callback normalised
+/// f32 samples (-1.0..1.0), so metering and writing only deal with one format.
+fn build_stream<T, F>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut on_samples: F,
+) -> Result<cpal::Stream, String>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+    F: FnMut(&[f32]) + Send + 'static,
+{
+    let mut converted: Vec<f32> = Vec::new();
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &_| {
+                converted.clear();
+                converted.extend(data.iter().map(|&s| s.to_sample::<f32>()));
+                on_samples(&converted);
+            },
+            |err| eprintln!("an error occurred on stream: {}", err),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// `build_stream` for whatever sample format the device config uses
+pub(crate) fn build_any_stream<F>(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    on_samples: F,
+) -> Result<cpal::Stream, String>
+where
+    F: FnMut(&[f32]) + Send + 'static,
+{
+    let stream_config: cpal::StreamConfig = config.clone().into();
+    match config.sample_format() {
+        cpal::SampleFormat::I8 => build_stream::<i8, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::I16 => build_stream::<i16, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::I24 => build_stream::<cpal::I24, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::I32 => build_stream::<i32, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::I64 => build_stream::<i64, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::U8 => build_stream::<u8, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::U16 => build_stream::<u16, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::U32 => build_stream::<u32, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::U64 => build_stream::<u64, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::F32 => build_stream::<f32, _>(device, &stream_config, on_samples),
+        cpal::SampleFormat::F64 => build_stream::<f64, _>(device, &stream_config, on_samples),
+        other => Err(format!("Unsupported sample format: {}", other)),
+    }
+}
+
+fn recordings_dir<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<std::path::PathBuf, String> {
+    Ok(app.path().app_data_dir().map_err(|e| e.to_string())?.join("recordings"))
+}
+
+/// Unique per take, so concurrent instances or users never overwrite each other
+fn archive_file_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    let dir = recordings_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create recordings folder: {}", e))?;
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    Ok(dir.join(format!("recording_{}.wav", stamp)))
+}
+
+/// Whether `path` is a take's archive WAV rather than a file the user picked
+pub(crate) fn is_archive<R: tauri::Runtime>(app: &tauri::AppHandle<R>, path: &std::path::Path) -> bool {
+    let dir = recordings_dir(app).ok().and_then(|dir| dir.canonicalize().ok());
+    let parent = path.parent().and_then(|parent| parent.canonicalize().ok());
+    dir.is_some() && parent == dir
+}
+
+/// Delete a take's archive WAV once it is no longer needed. Anything outside the
+/// recordings folder is left alone, so files the user picked are never removed.
+pub(crate) fn remove_archive<R: tauri::Runtime>(app: &tauri::AppHandle<R>, path: &std::path::Path) {
+    if is_archive(app, path) {
+        if let Err(e) = std::fs::remove_file(path) {
+            eprintln!("Failed to delete recording {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Delete the archive of a take that was thrown away.
+#[tauri::command]
+pub fn discard_recording(app_handle: tauri::AppHandle, path: String) {
+    remove_archive(&app_handle, std::path::Path::new(&path));
+}
+
+#[tauri::command]
+pub fn list_input_devices() -> Result<Vec<InputDeviceInfo>, String> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+
+    let devices = host.input_devices().map_err(|e| e.to_string())?;
+    let mut result = Vec::new();
+
+    for device in devices {
+        // Devices can disappear between enumeration and query, skip them silently
+        let name = match device.name() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        let configs = match device.supported_input_configs() {
+            Ok(configs) => configs
+                .map(|c| InputConfigInfo {
+                    channels: c.channels(),
+                    min_sample_rate: c.min_sample_rate().0,
+                    max_sample_rate: c.max_sample_rate().0,
+                    sample_format: c.sample_format().to_string(),
+                })
+                .collect(),
+            Err(_) => continue,
+        };
+
+        result.push(InputDeviceInfo {
+            id: device_id(&host, &name),
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            is_loopback: loopback::is_virtual_loopback(&name),
+            name,
+            configs,
+        });
+    }
+
+    result.extend(loopback::list_sources());
+    Ok(result)
+}
+
+/// Default and upper bound for the warm-mic pre-roll
+const DEFAULT_PREROLL_MS: u32 = 300;
+const MAX_PREROLL_MS: u32 = 2000;
+
+/// Open and start an input stream. The callback feeds whatever the shared state says:
+/// the current take while `capturing`, otherwise the pre-roll ring.
+fn open_input_stream(
+    app_handle: &tauri::AppHandle,
+    state: &AudioState,
+    device_id: Option<&str>,
+) -> Result<crate::state::SendStream, String> {
+    let host = cpal::default_host();
+    let (device, fell_back) = resolve_input_device(&host, device_id)?;
+    if fell_back {
+        let fallback = device.name().unwrap_or_else(|_| "default".to_string());
+        eprintln!("Input device {:?} not found, using {}", device_id, fallback);
+        let _ = app_handle.emit("input-device-fallback", DeviceFallback {
+            requested: device_id.unwrap_or_default().to_string(),
+            fallback,
+        });
+    }
     let config = device.default_input_config().map_err(|e| e.to_string())?;
-    let spec = hound::WavSpec {
-        channels: config.channels(),
+    let mut resampler = Resampler::new(config.sample_rate().0, WHISPER_SAMPLE_RATE)?;
+    *state.stream_device.lock().unwrap() = Some(CaptureDevice {
+        name: device.name().unwrap_or_else(|_| "unknown".to_string()),
         sample_rate: config.sample_rate().0,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
+        channels: config.channels(),
+    });
+
+    let sink = state.clone();
+    let channels = config.channels() as usize;
+    let mut mono: Vec<f32> = Vec::new();
+    let mut resampled: Vec<f32> = Vec::new();
+
+    let on_samples = move |data: &[f32]| {
+        // Compute RMS amplitude for this chunk
+        let rms = if data.is_empty() {
+            0.0f32
+        } else {
+            // Calculate mean (DC offset)
+            let mean: f32 = data.iter().sum::<f32>() / data.len() as f32;
+            // Calculate RMS without the DC offset
+            let sum_sq: f32 = data.iter().map(|&s| (s - mean) * (s - mean)).sum();
+            (sum_sq / data.len() as f32).sqrt()
+        };
+
+        // Track rolling current level (smoothed)
+        if let Ok(mut lvl) = sink.current_level.lock() {
+            *lvl = rms;
+        }
+
+        mono.clear();
+        resampled.clear();
+        dsp::downmix(data, channels, &mut mono);
+        resampler.process(&mono, &mut resampled);
+
+        // Checked under the pre-roll lock so start_recording can hand over without a gap
+        {
+            let mut preroll = sink.preroll.lock().unwrap();
+            if !sink.capturing.load(Ordering::SeqCst) {
+                preroll.push(&resampled);
+                return;
+            }
+        }
+        if sink.is_paused.load(Ordering::SeqCst) {
+            return;
+        }
 
-    let path = std::env::temp_dir().join("whisper_desktop_recording.wav");
-    let writer = hound::WavWriter::create(&path, spec).map_err(|e| e.to_string())?;
-    let writer = Arc::new(Mutex::new(Some(writer)));
-    let writer_clone = writer.clone();
+        // Track overall peak for silence detection
+        let peak = data.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
+        if let Ok(mut max_guard) = sink.max_amplitude.lock() {
+            if peak > *max_guard {
+                *max_guard = peak;
+            }
+        }
 
-    let err_fn = move |err| {
-        eprintln!("an error occurred on stream: {}", err);
+        feed_take(&sink, CaptureSource::Mic, &resampled);
     };
 
-    let stream = match config.sample_format() {
-        cpal::SampleFormat::F32 => device.build_input_stream(
-            &config.into(),
-            move |data: &[f32], _: &_| {
-                // Compute RMS amplitude for this chunk
-                let rms = if data.is_empty() {
-                    0.0f32
-                } else {
-                    // Calculate mean (DC offset)
-                    let mean: f32 = data.iter().sum::<f32>() / data.len() as f32;
-                    // Calculate RMS without the DC offset
-                    let sum_sq: f32 = data.iter().map(|&s| (s - mean) * (s - mean)).sum();
-                    (sum_sq / data.len() as f32).sqrt()
-                };
-
-                // Track rolling current level (smoothed)
-                if let Ok(mut lvl) = current_level.lock() {
-                    *lvl = rms;
-                }
+    let stream = build_any_stream(&device, &config, on_samples)?;
 
-                // Track overall peak for silence detection
-                let peak = data.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
-                if let Ok(mut max_guard) = max_amp.lock() {
-                    if peak > *max_guard {
-                        *max_guard = peak;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok(crate::state::SendStream(stream))
+}
+
+/// Route 16kHz mono audio from one source into the take. With mic and system audio
+/// both recording, it goes through the aligner and comes out mixed or as two tracks.
+pub(crate) fn feed_take(state: &AudioState, source: CaptureSource, data: &[f32]) {
+    let mut guard = state.dual.lock().unwrap();
+    match guard.as_mut() {
+        Some(dual) => {
+            let (me, them) = dual.push(source, data);
+            write_aligned(state, dual.mode, &me, &them);
+        }
+        None => append_to_take(state, data, None),
+    }
+}
+
+fn write_aligned(state: &AudioState, mode: LoopbackMode, me: &[f32], them: &[f32]) {
+    if me.is_empty() {
+        return;
+    }
+    match mode {
+        LoopbackMode::Mix => {
+            let mixed: Vec<f32> = me.iter().zip(them).map(|(a, b)| (a + b).clamp(-1.0, 1.0)).collect();
+            append_to_take(state, &mixed, None);
+        }
+        LoopbackMode::Separate => append_to_take(state, me, Some(them)),
+    }
+}
+
+/// Add 16kHz audio to the in-memory take and the archive, if any, and run auto-stop VAD.
+/// `them` is the system-audio track of a separate-channel take, as long as `data`.
+fn append_to_take(state: &AudioState, data: &[f32], them: Option<&[f32]>) {
+    if let Ok(mut buf) = state.samples.lock() {
+        buf.extend_from_slice(data);
+    }
+    if let Some(them) = them {
+        if let Ok(mut buf) = state.them.lock() {
+            buf.extend_from_slice(them);
+        }
+    }
+
+    if let Ok(mut guard) = state.writer.lock() {
+        if let Some(w) = guard.as_mut() {
+            let amplitude = i16::MAX as f32;
+            match them {
+                // Stereo archive: me on the left, them on the right
+                Some(them) => {
+                    for (&l, &r) in data.iter().zip(them) {
+                        w.write_sample((l * amplitude) as i16).ok();
+                        w.write_sample((r * amplitude) as i16).ok();
                     }
                 }
-
-                if let Ok(mut guard) = writer_clone.lock() {
-                    if let Some(w) = guard.as_mut() {
-                        for &sample in data {
-                            let amplitude = i16::MAX as f32;
-                            w.write_sample((sample * amplitude) as i16).ok();
-                        }
+                None => {
+                    for &sample in data {
+                        w.write_sample((sample * amplitude) as i16).ok();
                     }
                 }
-            },
-            err_fn,
-            None,
-        ),
-        _ => return Err("Unsupported sample format (only F32 implemented)".into()),
+            }
+        }
     }
-    .map_err(|e| e.to_string())?;
 
-    stream.play().map_err(|e| e.to_string())?;
+    if let Ok(mut guard) = state.vad.lock() {
+        if let Some((vad, hang_ms)) = guard.as_mut() {
+            if vad.push(data) {
+                state.speech_started.store(true, Ordering::SeqCst);
+            }
+            if vad.heard_speech() && vad.trailing_silence_ms() >= *hang_ms {
+                state.silence_reached.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Keep the input stream open between takes so the start of speech is never lost.
+/// Passing `enabled: false` closes the stream unless a take is in progress.
+#[tauri::command]
+pub fn set_warm_mic(
+    state: State<'_, AudioState>,
+    app_handle: tauri::AppHandle,
+    enabled: bool,
+    device_id: Option<String>,
+    preroll_ms: Option<u32>,
+) -> Result<(), String> {
+    let recording = *state.is_recording.lock().unwrap();
+
+    if !enabled {
+        *state.warm_mic.lock().unwrap() = None;
+        {
+            let mut preroll = state.preroll.lock().unwrap();
+            preroll.samples.clear();
+            preroll.capacity = 0;
+        }
+        if !recording {
+            *state.stream.lock().unwrap() = None;
+        }
+        return Ok(());
+    }
+
+    // System audio has no start of speech to miss and is never kept open
+    if device_id.as_deref().map(loopback::is_loopback_id).unwrap_or(false) {
+        *state.warm_mic.lock().unwrap() = None;
+        if !recording {
+            *state.stream.lock().unwrap() = None;
+        }
+        return Ok(());
+    }
+
+    let preroll_ms = preroll_ms.unwrap_or(DEFAULT_PREROLL_MS).min(MAX_PREROLL_MS);
+    state.preroll.lock().unwrap().capacity = (WHISPER_SAMPLE_RATE as usize * preroll_ms as usize) / 1000;
+
+    let previous = state.warm_mic.lock().unwrap().replace(WarmMic {
+        device_id: device_id.clone(),
+        preroll_ms,
+    });
+    let same_device = previous.map(|w| w.device_id) == Some(device_id.clone());
+
+    // A running take keeps its stream; the new device is picked up on the next start
+    let stream_open = state.stream.lock().unwrap().is_some();
+    let keep_stream = recording || (stream_open && same_device);
+    if !keep_stream {
+        *state.stream.lock().unwrap() = None;
+        let stream = open_input_stream(&app_handle, &state, device_id.as_deref())?;
+        *state.stream.lock().unwrap() = Some(stream);
+    }
+
+    Ok(())
+}
+
+/// Longest take the given model and the free memory allow right now.
+#[tauri::command]
+pub fn get_recording_limit(model: String) -> RecordingLimit {
+    recording_limit(&model, crate::commands::system::available_memory())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_recording(
+    state: State<'_, AudioState>,
+    app_handle: tauri::AppHandle,
+    device_id: Option<String>,
+    archive: Option<bool>,
+    auto_stop: Option<AutoStopOptions>,
+    max_duration_secs: Option<u64>,
+    live: Option<LiveOptions>,
+    loopback: Option<LoopbackOptions>,
+) -> Result<(), String> {
+    // A live session of a take that was stopped but never finished would clear this take's samples
+    if !*state.is_recording.lock().unwrap() {
+        crate::commands::live::cancel(&app_handle).await;
+    }
+    let mut is_rec = state.is_recording.lock().unwrap();
+    if *is_rec {
+        return Err("Already recording".into());
+    }
+
+    // Everything that can fail is opened before the take state is touched, so an error
+    // leaves nothing behind. The streams drop samples until `capturing`.
+    // System audio picked as the only input means no mic stream for this take
+    let system_only = device_id.as_deref().map(loopback::is_loopback_id).unwrap_or(false);
+    let loopback = if system_only { None } else { loopback };
+    let separate = loopback.as_ref().map(|l| l.mode == LoopbackMode::Separate).unwrap_or(false);
+
+    let system_stream = match (&device_id, &loopback) {
+        (Some(id), _) if system_only => Some(loopback::open(&state, id, true)?),
+        (_, Some(lb)) => Some(loopback::open(&state, &lb.device_id, false)?),
+        _ => None,
+    };
 
-    *state.stream.lock().unwrap() = Some(crate::state::SendStream(stream));
+    // Reuse the warm stream when it is listening on the requested device
+    let warm = state.warm_mic.lock().unwrap().clone();
+    let stream_open = state.stream.lock().unwrap().is_some();
+    let reuse_warm = stream_open && warm.as_ref().map(|w| w.device_id == device_id).unwrap_or(false);
+    let mic_stream = if system_only || reuse_warm {
+        None
+    } else {
+        *state.stream.lock().unwrap() = None;
+        Some(open_input_stream(&app_handle, &state, device_id.as_deref())?)
+    };
+
+    // Samples live in memory; the WAV on disk is only an optional archive copy
+    let archive = if archive.unwrap_or(false) {
+        let path = archive_file_path(&app_handle)?;
+        // Record straight to Whisper's input format, whatever the device delivers
+        let spec = hound::WavSpec {
+            channels: if separate { 2 } else { 1 },
+            sample_rate: WHISPER_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        match hound::WavWriter::create(&path, spec) {
+            Ok(writer) => Some((writer, path)),
+            Err(e) => {
+                let _ = std::fs::remove_file(&path);
+                return Err(e.to_string());
+            }
+        }
+    } else {
+        None
+    };
+    let (writer, archive_path) = archive.unzip();
+    *state.writer.lock().unwrap() = writer;
+    *state.archive_path.lock().unwrap() = archive_path;
+
+    if let Ok(mut amp) = state.max_amplitude.lock() {
+        *amp = 0.0;
+    }
+    if let Ok(mut lvl) = state.current_level.lock() {
+        *lvl = 0.0;
+    }
+
+    if let Ok(mut buf) = state.samples.lock() {
+        buf.clear();
+    }
+    state.them.lock().unwrap().clear();
+
+    state.is_paused.store(false, Ordering::SeqCst);
+    state.speech_started.store(false, Ordering::SeqCst);
+    state.silence_reached.store(false, Ordering::SeqCst);
+    *state.vad.lock().unwrap() = auto_stop
+        .as_ref()
+        .map(|opts| (EnergyVad::new(opts.threshold), opts.hang_ms));
+    *state.dual.lock().unwrap() = loopback.as_ref().map(|l| DualCapture::new(l.mode));
+
+    if let Some(stream) = mic_stream {
+        state.capturing.store(true, Ordering::SeqCst);
+        *state.stream.lock().unwrap() = Some(stream);
+        if let Some(mut w) = warm {
+            w.device_id = device_id.clone();
+            *state.warm_mic.lock().unwrap() = Some(w);
+        }
+    } else if reuse_warm {
+        // Switch the callback over while holding the ring, then prepend what it heard
+        let mut preroll = state.preroll.lock().unwrap();
+        state.capturing.store(true, Ordering::SeqCst);
+        let heard: Vec<f32> = preroll.samples.drain(..).collect();
+        feed_take(&state, CaptureSource::Mic, &heard);
+    } else {
+        *state.stream.lock().unwrap() = None;
+        state.capturing.store(true, Ordering::SeqCst);
+    }
+    let (system_stream, system_device) = system_stream.unzip();
+    *state.loopback_stream.lock().unwrap() = system_stream;
+    if system_only {
+        *state.take_device.lock().unwrap() = system_device;
+        *state.take_system_device.lock().unwrap() = None;
+    } else {
+        *state.take_device.lock().unwrap() = state.stream_device.lock().unwrap().clone();
+        *state.take_system_device.lock().unwrap() = system_device;
+    }
+    state.clock.lock().unwrap().start();
     *is_rec = true;
 
+    // The worker polls the take until capture stops, so start it only once the take is live
+    *state.live.lock().unwrap() = live.map(|opts| crate::commands::live::spawn(app_handle.clone(), opts));
 
     // Spawn a thread that emits audio level to the main window every ~60ms while recording
     let is_rec_arc = state.is_recording.clone();
     let level_arc = state.current_level.clone();
+    let speech_started = state.speech_started.clone();
+    let silence_reached = state.silence_reached.clone();
+    let is_paused = state.is_paused.clone();
+    let clock = state.clock.clone();
     let app = app_handle.clone();
-    let start_time = std::time::Instant::now();
+    let mut auto_stop = auto_stop;
+    let max_ms = max_duration_secs.map(|secs| secs * 1000);
+    let warn_ms = max_ms.map(|max| max.saturating_sub(LIMIT_WARNING_SECS.min(max / 1000 / 2) * 1000));
+    let mut warned = false;
+    let mut limit_reached = false;
     std::thread::spawn(move || {
         // Set initial 00:00 immediately
         if let Some(tray) = app.tray_by_id("main") {
@@ -116,11 +673,46 @@ pub async fn start_recording(
                 }
                 break;
             }
-            let level = *level_arc.lock().unwrap();
+            if speech_started.swap(false, Ordering::SeqCst) {
+                let _ = app.emit("vad-speech-start", ());
+            }
+            if silence_reached.load(Ordering::SeqCst) {
+                if let Some(opts) = auto_stop.take() {
+                    auto_stop_and_transcribe(&app, "silence", opts);
+                }
+                // Next iteration sees the take has ended and clears the tray
+                continue;
+            }
+
+            let active_ms = clock.lock().unwrap().active_ms();
+            if let (Some(max_ms), Some(warn_ms)) = (max_ms, warn_ms) {
+                if !warned && active_ms >= warn_ms {
+                    warned = true;
+                    let _ = app.emit("recording-limit-warning", RecordingLimitWarning {
+                        remaining_secs: max_ms.saturating_sub(active_ms) / 1000,
+                        max_secs: max_ms / 1000,
+                    });
+                }
+                if !limit_reached && active_ms >= max_ms {
+                    limit_reached = true;
+                    if let Some(opts) = auto_stop.take() {
+                        auto_stop_and_transcribe(&app, "max_duration", opts);
+                        continue;
+                    }
+                    // Stop taking audio right away; the frontend ends the take as usual
+                    let state = app.state::<AudioState>();
+                    halt_capture(&state);
+                    clock.lock().unwrap().pause();
+                    let _ = app.emit("recording-limit-reached", max_ms / 1000);
+                }
+            }
+
+            // Meter drops to zero and the tray timer holds while paused or past the limit
+            let level = if limit_reached || is_paused.load(Ordering::SeqCst) { 0.0 } else { *level_arc.lock().unwrap() };
             if let Some(main_win) = app.get_webview_window("main") {
                 let _ = main_win.emit("audio-level", level as f64);
             }
-            let elapsed = start_time.elapsed().as_secs();
+            let elapsed = active_ms / 1000;
             if elapsed > last_second {
                 last_second = elapsed;
                 let time_str = format!("{:02}:{:02}", elapsed / 60, elapsed % 60);
@@ -134,28 +726,190 @@ pub async fn start_recording(
     Ok(())
 }
 
+/// Stop taking samples without ending the take. Returns the recorded time so far in ms.
+#[tauri::command]
+pub fn pause_recording(state: State<'_, AudioState>) -> Result<u64, String> {
+    if !*state.is_recording.lock().unwrap() {
+        return Err("Not recording".into());
+    }
+    if !state.capturing.load(Ordering::SeqCst) {
+        return Err("Recording limit reached".into());
+    }
+    if state.is_paused.swap(true, Ordering::SeqCst) {
+        return Err("Already paused".into());
+    }
+    let mut clock = state.clock.lock().unwrap();
+    clock.pause();
+    Ok(clock.active_ms())
+}
+
+/// Continue a paused take into the same buffer and archive file.
+#[tauri::command]
+pub fn resume_recording(state: State<'_, AudioState>) -> Result<u64, String> {
+    if !*state.is_recording.lock().unwrap() {
+        return Err("Not recording".into());
+    }
+    if !state.capturing.load(Ordering::SeqCst) {
+        return Err("Recording limit reached".into());
+    }
+    if !state.is_paused.load(Ordering::SeqCst) {
+        return Err("Not paused".into());
+    }
+    let mut clock = state.clock.lock().unwrap();
+    clock.resume();
+    state.is_paused.store(false, Ordering::SeqCst);
+    Ok(clock.active_ms())
+}
+
 #[tauri::command]
 pub async fn stop_recording(
     state: State<'_, AudioState>,
-) -> Result<String, String> {
+) -> Result<RecordingResult, String> {
+    finish_recording(&state)
+}
+
+/// End the current take. Shared by `stop_recording` and the auto-stop paths.
+pub(crate) fn finish_recording(state: &AudioState) -> Result<RecordingResult, String> {
     let mut is_rec = state.is_recording.lock().unwrap();
     if !*is_rec {
         return Err("Not recording".into());
     }
 
-    {
-        let mut stream_guard = state.stream.lock().unwrap();
-        *stream_guard = None;
+    halt_capture(state);
+    // Whatever the aligner still holds belongs to the end of the take
+    let dual = state.dual.lock().unwrap().take();
+    if let Some(mut dual) = dual {
+        let (me, them) = dual.flush();
+        write_aligned(state, dual.mode, &me, &them);
+    }
+    // Close an open pause so the recorded spans cover the whole take
+    if state.is_paused.swap(false, Ordering::SeqCst) {
+        state.clock.lock().unwrap().resume();
     }
 
     *is_rec = false;
 
+    // Finalize the archive so the header is valid before anyone reads it
+    let archive_path = state.archive_path.lock().unwrap().take();
+    if let Some(writer) = state.writer.lock().unwrap().take() {
+        writer.finalize().map_err(|e| format!("Failed to finalize recording: {}", e))?;
+    }
+
     let max_amp = *state.max_amplitude.lock().unwrap();
     if max_amp < 0.02 {
+        // Nothing will transcribe a silent take, so its archive goes too
+        if let Some(path) = &archive_path {
+            let _ = std::fs::remove_file(path);
+        }
         return Err("SILENCE_DETECTED".into());
     }
 
-    let path = std::env::temp_dir().join("whisper_desktop_recording.wav");
-    Ok(path.to_string_lossy().into_owned())
+    let samples = state.samples.lock().unwrap();
+    let rms = dsp::vad::frame_rms(&samples);
+    Ok(RecordingResult {
+        path: archive_path.map(|p| p.to_string_lossy().into_owned()),
+        duration_ms: samples.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64,
+        pauses: state.clock.lock().unwrap().pauses.clone(),
+        device: state.take_device.lock().unwrap().clone(),
+        system_device: state.take_system_device.lock().unwrap().clone(),
+        peak: max_amp.min(1.0),
+        rms,
+        speech_ratio: dsp::vad::speech_ratio(&samples),
+        waveform: dsp::envelope(&samples, WAVEFORM_POINTS),
+    })
 }
 
+/// Stop feeding the take without finalizing it. The stream closes unless the mic is warm.
+fn halt_capture(state: &AudioState) {
+    state.capturing.store(false, Ordering::SeqCst);
+    *state.vad.lock().unwrap() = None;
+    *state.loopback_stream.lock().unwrap() = None;
+    // A warm mic keeps listening into the pre-roll ring
+    if state.warm_mic.lock().unwrap().is_none() {
+        let mut stream_guard = state.stream.lock().unwrap();
+        *stream_guard = None;
+    }
+}
+
+/// Stop the take from the level thread and run the transcription in the background.
+/// The UI and overlay only learn about it through events.
+fn auto_stop_and_transcribe(app: &tauri::AppHandle, reason: &str, opts: AutoStopOptions) {
+    let state = app.state::<AudioState>();
+    let result = finish_recording(&state);
+
+    let _ = app.emit("overlay-stop", ());
+    let _ = app.emit("recording-auto-stopped", AutoStopped {
+        reason: reason.to_string(),
+        path: result.as_ref().ok().and_then(|r| r.path.clone()),
+        recording: result.as_ref().ok().cloned(),
+    });
+
+    if let Err(e) = result {
+        let _ = app.emit("auto-transcription", AutoTranscription { text: None, transcript: None, error: Some(e) });
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        // A live session already has most of the take transcribed
+        let result = match crate::commands::live::finish(&app).await {
+            Some(result) => result.map(|text| (text, None)),
+            None => crate::commands::local::transcribe_recording(
+                app.clone(),
+                opts.model,
+                opts.use_gpu,
+                opts.translate,
+                Some(opts.preprocess),
+                Some(opts.language),
+                Some(opts.decoding),
+                Some(opts.spoken_commands),
+                opts.vocabulary_profile,
+            )
+            .await
+            .map(|t| (t.text.clone(), Some(t))),
+        };
+        let payload = match result {
+            Ok((text, transcript)) => AutoTranscription { text: Some(text), transcript, error: None },
+            Err(e) => AutoTranscription { text: None, transcript: None, error: Some(e) },
+        };
+        let _ = app.emit("auto-transcription", payload);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn without_memory_info_the_model_sets_the_limit() {
+        let limit = recording_limit("base", None);
+        assert_eq!((limit.max_secs, limit.limited_by.as_str()), (60 * 60, "model"));
+    }
+
+    #[test]
+    fn plenty_of_memory_leaves_the_model_limit() {
+        let limit = recording_limit("tiny", Some(64 * 1024 * MB));
+        assert_eq!((limit.max_secs, limit.limited_by.as_str()), (90 * 60, "model"));
+    }
+
+    #[test]
+    fn memory_caps_the_take_below_the_model_limit() {
+        // Half of what is left after loading the model
+        let limit = recording_limit("base", Some(400 * MB + 1000 * BYTES_PER_RECORDED_SEC));
+        assert_eq!((limit.max_secs, limit.limited_by.as_str()), (500, "memory"));
+    }
+
+    #[test]
+    fn memory_smaller_than_the_model_still_allows_a_minute() {
+        let limit = recording_limit("large", Some(1000 * MB));
+        assert_eq!((limit.max_secs, limit.limited_by.as_str()), (60, "memory"));
+        assert_eq!(recording_limit("medium", Some(0)).max_secs, 60);
+    }
+
+    #[test]
+    fn unknown_models_get_the_large_budget() {
+        assert_eq!(recording_limit("Large-v3", None).max_secs, 10 * 60);
+    }
+}
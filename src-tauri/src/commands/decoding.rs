@@ -0,0 +1,153 @@
+use serde::Deserialize;
+use whisper_rs::{FullParams, SamplingStrategy};
+
+/// Named decoding settings for the UI
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodingPreset {
+    /// Greedy with whisper.cpp's own fallback and each command's thresholds, the settings used before presets
+    Standard,
+    /// Single greedy pass, a segment that looks wrong is not decoded again
+    Fast,
+    /// Greedy, but a segment that looks wrong is retried hotter, keeping the best of 5 samples
+    Balanced,
+    /// Beam search with the same fallback
+    Accurate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum Strategy {
+    Greedy { best_of: i32 },
+    BeamSearch { beam_size: i32 },
+}
+
+/// Explicit decoding settings. Unset thresholds keep each command's own.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct DecodingOptions {
+    #[serde(flatten)]
+    pub strategy: Strategy,
+    /// Starting temperature, 0 for deterministic decoding
+    #[serde(default)]
+    pub temperature: f32,
+    /// Decode again this much hotter when a segment fails the thresholds below, 0 to never retry
+    pub temperature_inc: Option<f32>,
+    /// Retry when the token entropy is above this (repetitive output)
+    pub entropy_thold: Option<f32>,
+    /// Retry when the average token log probability is below this
+    pub logprob_thold: Option<f32>,
+}
+
+/// Decoding profile: a preset name or explicit settings
+#[derive(Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum Decoding {
+    Preset(DecodingPreset),
+    Custom(DecodingOptions),
+}
+
+impl Default for Decoding {
+    fn default() -> Self {
+        Decoding::Preset(DecodingPreset::Standard)
+    }
+}
+
+impl DecodingPreset {
+    fn options(self) -> DecodingOptions {
+        match self {
+            DecodingPreset::Standard => DecodingOptions {
+                strategy: Strategy::Greedy { best_of: 1 },
+                temperature: 0.0,
+                temperature_inc: None,
+                entropy_thold: None,
+                logprob_thold: None,
+            },
+            DecodingPreset::Fast => DecodingOptions {
+                strategy: Strategy::Greedy { best_of: 1 },
+                temperature: 0.0,
+                // Without this whisper.cpp still retries at 0.2 steps
+                temperature_inc: Some(0.0),
+                entropy_thold: None,
+                logprob_thold: None,
+            },
+            DecodingPreset::Balanced => DecodingOptions {
+                strategy: Strategy::Greedy { best_of: 5 },
+                temperature: 0.0,
+                temperature_inc: Some(0.2),
+                entropy_thold: Some(2.4),
+                logprob_thold: None,
+            },
+            DecodingPreset::Accurate => DecodingOptions {
+                strategy: Strategy::BeamSearch { beam_size: 5 },
+                temperature: 0.0,
+                temperature_inc: Some(0.2),
+                entropy_thold: Some(2.4),
+                logprob_thold: None,
+            },
+        }
+    }
+}
+
+impl Decoding {
+    pub fn options(self) -> DecodingOptions {
+        match self {
+            Decoding::Preset(preset) => preset.options(),
+            Decoding::Custom(options) => options,
+        }
+    }
+}
+
+impl DecodingOptions {
+    /// Fresh parameters for this strategy
+    pub(crate) fn params(&self) -> FullParams<'static, 'static> {
+        let strategy = match self.strategy {
+            Strategy::Greedy { best_of } => SamplingStrategy::Greedy { best_of: best_of.max(1) },
+            // Negative patience leaves whisper.cpp's default
+            Strategy::BeamSearch { beam_size } => SamplingStrategy::BeamSearch { beam_size: beam_size.max(1), patience: -1.0 },
+        };
+        let mut params = FullParams::new(strategy);
+        params.set_temperature(self.temperature.max(0.0));
+        params
+    }
+
+    /// Override the fallback settings that were chosen. Call after the command set its own thresholds.
+    pub(crate) fn apply_fallback(&self, params: &mut FullParams) {
+        if let Some(inc) = self.temperature_inc {
+            params.set_temperature_inc(inc.max(0.0));
+        }
+        if let Some(entropy) = self.entropy_thold {
+            params.set_entropy_thold(entropy);
+        }
+        if let Some(logprob) = self.logprob_thold {
+            params.set_logprob_thold(logprob);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_differ() {
+        let standard = DecodingPreset::Standard.options();
+        let fast = DecodingPreset::Fast.options();
+        let balanced = DecodingPreset::Balanced.options();
+        let accurate = DecodingPreset::Accurate.options();
+        assert_ne!(fast, balanced);
+        assert_ne!(balanced, accurate);
+        assert_ne!(fast, accurate);
+        assert_ne!(standard, fast);
+        assert_eq!(fast.temperature_inc, Some(0.0));
+        assert!(balanced.temperature_inc.is_some_and(|inc| inc > 0.0));
+    }
+
+    #[test]
+    fn default_keeps_stock_fallback() {
+        let options = Decoding::default().options();
+        assert_eq!(options.strategy, Strategy::Greedy { best_of: 1 });
+        assert_eq!(options.temperature_inc, None);
+        assert_eq!(options.entropy_thold, None);
+        assert_eq!(options.logprob_thold, None);
+    }
+}
@@ -0,0 +1,25 @@
+// Hallucination filter settings, shared by dictation, live transcription and SRT.
+
+use tauri::{AppHandle, Runtime};
+use crate::postprocess::{self, filter::FilterRules};
+
+#[tauri::command]
+pub fn get_hallucination_filter<R: Runtime>(app: AppHandle<R>) -> Result<FilterRules, String> {
+    postprocess::load_filter(&app)
+}
+
+/// Returns the saved rules, with blank phrases and words dropped.
+#[tauri::command]
+pub fn save_hallucination_filter<R: Runtime>(app: AppHandle<R>, mut rules: FilterRules) -> Result<FilterRules, String> {
+    for list in [&mut rules.phrases, &mut rules.annotation_words] {
+        *list = list.iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+    }
+    postprocess::save_filter(&app, &rules)?;
+    Ok(rules)
+}
+
+/// Back to the built-in rules
+#[tauri::command]
+pub fn reset_hallucination_filter<R: Runtime>(app: AppHandle<R>) -> Result<FilterRules, String> {
+    save_hallucination_filter(app, FilterRules::default())
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use whisper_rs::{get_lang_id, get_lang_str, WhisperState};
+use crate::dsp::WHISPER_SAMPLE_RATE;
+
+/// Source language of a transcription
+#[derive(Clone, Default, Deserialize)]
+pub struct LanguageOptions {
+    /// Whisper language code ("en", "ne", ...); unset or "auto" to detect it
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Only detect among these codes, empty for any language
+    #[serde(default)]
+    pub allowed: Vec<String>,
+}
+
+/// Language a transcription was decoded in
+#[derive(Clone, Copy, Serialize)]
+pub struct DetectedLanguage {
+    pub code: &'static str,
+    /// Detection confidence, relative to the allow-list if there is one.
+    /// `None` when the caller fixed the language.
+    pub probability: Option<f32>,
+}
+
+/// Whisper detects the language from the first 30 s
+const DETECT_SECS: usize = 30;
+
+fn lang_id(code: &str) -> Result<i32, String> {
+    get_lang_id(code.trim()).ok_or_else(|| format!("Unknown language: {}", code))
+}
+
+/// Most likely language among `allowed` (any when empty) and its probability,
+/// renormalized over the allow-list so the allowed languages sum to 1
+fn pick(best: i32, probs: &[f32], allowed: &[i32]) -> (i32, f32) {
+    let prob = |id: i32| probs.get(id as usize).copied().unwrap_or(0.0);
+    if allowed.is_empty() {
+        return (best, prob(best));
+    }
+    let total: f32 = allowed.iter().map(|&id| prob(id)).sum();
+    let id = allowed
+        .iter()
+        .copied()
+        .max_by(|&a, &b| prob(a).total_cmp(&prob(b)))
+        .unwrap_or(best);
+    (id, if total > 0.0 { prob(id) / total } else { 0.0 })
+}
+
+/// Pick the language to decode `samples` in: the one asked for, or the most likely one
+/// (among the allowed ones). Runs Whisper's language detection on `state` when needed.
+pub(crate) fn resolve(
+    state: &mut WhisperState,
+    samples: &[f32],
+    opts: &LanguageOptions,
+    threads: usize,
+) -> Result<DetectedLanguage, String> {
+    if let Some(code) = opts.language.as_deref().filter(|c| !c.is_empty() && *c != "auto") {
+        let code = get_lang_str(lang_id(code)?).ok_or_else(|| format!("Unknown language: {}", code))?;
+        return Ok(DetectedLanguage { code, probability: None });
+    }
+
+    let allowed = opts.allowed.iter().map(String::as_str).map(lang_id).collect::<Result<Vec<_>, _>>()?;
+
+    let head = &samples[..samples.len().min(DETECT_SECS * WHISPER_SAMPLE_RATE as usize)];
+    state.pcm_to_mel(head, threads).map_err(|e| format!("Failed to compute spectrogram: {}", e))?;
+    let (best, probs) = state.lang_detect(0, threads).map_err(|e| format!("Failed to detect language: {}", e))?;
+
+    let (id, probability) = pick(best, &probs, &allowed);
+
+    let code = get_lang_str(id).ok_or("Language detection returned no language")?;
+    Ok(DetectedLanguage { code, probability: Some(probability) })
+}
+
+/// ISO 639-2 codes (bibliographic and terminology) of the languages Whisper knows
+const ISO_639_2: &[(&str, &str)] = &[
+    ("afr", "af"), ("alb", "sq"), ("amh", "am"), ("ara", "ar"), ("arm", "hy"), ("asm", "as"),
+    ("aze", "az"), ("bak", "ba"), ("baq", "eu"), ("bel", "be"), ("ben", "bn"), ("bod", "bo"),
+    ("bos", "bs"), ("bre", "br"), ("bul", "bg"), ("bur", "my"), ("cat", "ca"), ("ces", "cs"),
+    ("chi", "zh"), ("cym", "cy"), ("cze", "cs"), ("dan", "da"), ("deu", "de"), ("dut", "nl"),
+    ("ell", "el"), ("eng", "en"), ("est", "et"), ("eus", "eu"), ("fao", "fo"), ("fas", "fa"),
+    ("fin", "fi"), ("fra", "fr"), ("fre", "fr"), ("geo", "ka"), ("ger", "de"), ("glg", "gl"),
+    ("gre", "el"), ("guj", "gu"), ("hat", "ht"), ("hau", "ha"), ("haw", "haw"), ("heb", "he"),
+    ("hin", "hi"), ("hrv", "hr"), ("hun", "hu"), ("hye", "hy"), ("ice", "is"), ("ind", "id"),
+    ("isl", "is"), ("ita", "it"), ("jav", "jw"), ("jpn", "ja"), ("kan", "kn"), ("kat", "ka"),
+    ("kaz", "kk"), ("khm", "km"), ("kor", "ko"), ("lao", "lo"), ("lat", "la"), ("lav", "lv"),
+    ("lin", "ln"), ("lit", "lt"), ("ltz", "lb"), ("mac", "mk"), ("mal", "ml"), ("mao", "mi"),
+    ("mar", "mr"), ("may", "ms"), ("mkd", "mk"), ("mlg", "mg"), ("mlt", "mt"), ("mon", "mn"),
+    ("mri", "mi"), ("msa", "ms"), ("mya", "my"), ("nep", "ne"), ("nld", "nl"), ("nno", "nn"),
+    ("nob", "no"), ("nor", "no"), ("oci", "oc"), ("pan", "pa"), ("per", "fa"), ("pol", "pl"),
+    ("por", "pt"), ("pus", "ps"), ("ron", "ro"), ("rum", "ro"), ("rus", "ru"), ("san", "sa"),
+    ("sin", "si"), ("slk", "sk"), ("slo", "sk"), ("slv", "sl"), ("sna", "sn"), ("snd", "sd"),
+    ("som", "so"), ("spa", "es"), ("sqi", "sq"), ("srp", "sr"), ("sun", "su"), ("swa", "sw"),
+    ("swe", "sv"), ("tam", "ta"), ("tat", "tt"), ("tel", "te"), ("tgk", "tg"), ("tgl", "tl"),
+    ("tha", "th"), ("tib", "bo"), ("tuk", "tk"), ("tur", "tr"), ("ukr", "uk"), ("urd", "ur"),
+    ("uzb", "uz"), ("vie", "vi"), ("wel", "cy"), ("yid", "yi"), ("yor", "yo"), ("yue", "yue"),
+    ("zho", "zh"),
+];
+
+/// Whisper code for a track's language tag: ISO 639-1 or 639-2, or a BCP 47 tag like "pt-BR"
+pub(crate) fn from_track_tag(tag: &str) -> Option<&'static str> {
+    let primary = tag.split(['-', '_']).next()?.trim().to_lowercase();
+    if primary.is_empty() || !primary.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let code = match primary.as_str() {
+        // Whisper still uses the old code for Javanese
+        "jv" => "jw",
+        other => ISO_639_2.iter().find(|(iso, _)| *iso == other).map_or(other, |(_, code)| *code),
+    };
+    get_lang_id(code).and_then(get_lang_str)
+}
+
+impl LanguageOptions {
+    /// Take a track's language when the user left the language to detection,
+    /// unless the allow-list rules it out.
+    pub(crate) fn or_track_language(mut self, code: Option<&str>) -> Self {
+        let detecting = self.language.as_deref().is_none_or(|c| c.is_empty() || c == "auto");
+        if let Some(code) = code {
+            if detecting && (self.allowed.is_empty() || self.allowed.iter().any(|a| a == code)) {
+                self.language = Some(code.to_string());
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(code: &str) -> i32 {
+        lang_id(code).unwrap()
+    }
+
+    fn options(language: Option<&str>, allowed: &[&str]) -> LanguageOptions {
+        LanguageOptions {
+            language: language.map(str::to_string),
+            allowed: allowed.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn track_tags_map_to_whisper_codes() {
+        assert_eq!(from_track_tag("ger"), Some("de"));
+        assert_eq!(from_track_tag("deu"), Some("de"));
+        assert_eq!(from_track_tag("pt-BR"), Some("pt"));
+        assert_eq!(from_track_tag("EN"), Some("en"));
+        assert_eq!(from_track_tag("jv"), Some("jw"));
+        assert_eq!(from_track_tag("und"), None);
+        assert_eq!(from_track_tag("xx"), None);
+        assert_eq!(from_track_tag(""), None);
+    }
+
+    #[test]
+    fn explicit_language_beats_the_track() {
+        let opts = options(Some("fr"), &[]).or_track_language(Some("de"));
+        assert_eq!(opts.language.as_deref(), Some("fr"));
+        let opts = options(Some("auto"), &[]).or_track_language(Some("de"));
+        assert_eq!(opts.language.as_deref(), Some("de"));
+        let opts = options(None, &["en", "fr"]).or_track_language(Some("de"));
+        assert_eq!(opts.language, None);
+        let opts = options(None, &[]).or_track_language(None);
+        assert_eq!(opts.language, None);
+    }
+
+    #[test]
+    fn allow_list_renormalizes_the_probability() {
+        let mut probs = vec![0.0; 100];
+        probs[id("en") as usize] = 0.6;
+        probs[id("de") as usize] = 0.3;
+        probs[id("fr") as usize] = 0.1;
+
+        assert_eq!(pick(id("en"), &probs, &[]), (id("en"), 0.6));
+        let (picked, probability) = pick(id("en"), &probs, &[id("de"), id("fr")]);
+        assert_eq!(picked, id("de"));
+        assert!((probability - 0.75).abs() < 1e-6);
+        // Nothing allowed was heard at all
+        assert_eq!(pick(id("en"), &probs, &[id("ne")]), (id("ne"), 0.0));
+    }
+}
@@ -0,0 +1,336 @@
+use tauri::{AppHandle, Emitter, Manager};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::dsp::{ms_to_samples, preprocess::{self, PreprocessOptions}, samples_to_ms};
+use crate::inference;
+use crate::postprocess::{self, filter::FilterRules};
+use crate::state::{AudioState, LiveSession};
+use crate::transcript::{self, TranscriptSegment};
+use super::decoding::Decoding;
+use super::language::{self, LanguageOptions};
+use super::vocabulary;
+use super::local::{dictation_params, dictation_threads, resolve_model_path, with_context};
+
+/// Transcribe the take while it is being recorded
+#[derive(Clone, Deserialize)]
+pub struct LiveOptions {
+    pub model: String,
+    pub use_gpu: bool,
+    pub translate: bool,
+    #[serde(default)]
+    pub preprocess: PreprocessOptions,
+    /// Interpret spoken punctuation and edit commands in the final text
+    #[serde(default)]
+    pub spoken_commands: bool,
+    #[serde(default)]
+    pub decoding: Decoding,
+    #[serde(default)]
+    pub language: LanguageOptions,
+    /// Vocabulary lists of this profile are used besides the shared ones
+    #[serde(default)]
+    pub vocabulary_profile: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PartialTranscript {
+    /// Text that will not change any more
+    pub stable: String,
+    /// Best guess for the audio after `stable`, may be revised by the next pass
+    pub tentative: String,
+    pub is_final: bool,
+}
+
+/// Pause between passes over the rolling window
+const STEP_MS: u64 = 1000;
+/// Don't bother Whisper with less audio than this
+const MIN_WINDOW_MS: u64 = 1000;
+/// A segment must end this far before the live edge before it can be committed
+const SETTLE_MS: u64 = 1500;
+/// Commit settled segments even without agreement once the window grows past this,
+/// so each pass stays well inside Whisper's 30 s input
+const MAX_WINDOW_MS: u64 = 20_000;
+/// Tail of the committed text fed back as the prompt for the next window
+const PROMPT_CHARS: usize = 200;
+/// Prompt tokens kept free for that tail; the vocabulary gets the rest
+const TAIL_TOKENS: usize = 64;
+
+fn push_text(out: &mut String, text: &str) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+    if !out.is_empty() {
+        out.push(' ');
+    }
+    out.push_str(text);
+}
+
+/// What one pass settles for good
+#[derive(Debug, PartialEq)]
+struct Commit {
+    /// Leading segments of the pass that are committed
+    segments: usize,
+    /// Samples at the start of the window that later windows skip
+    samples: usize,
+}
+
+/// Commit the leading segments that ended `SETTLE_MS` before the live edge and that
+/// the previous pass agreed on. Past `MAX_WINDOW_MS` settled segments are committed
+/// without agreement, and a window without any segments is skipped up to the settle margin.
+fn decide_commit(segments: &[TranscriptSegment], previous: &[String], window_len: usize) -> Commit {
+    let window_ms = samples_to_ms(window_len);
+    let force = window_ms > MAX_WINDOW_MS;
+    let mut n_commit = 0;
+    for (i, seg) in segments.iter().enumerate() {
+        let settled = seg.end_ms + SETTLE_MS <= window_ms;
+        let agreed = previous.get(i) == Some(&seg.text);
+        if !settled || !(agreed || force) {
+            break;
+        }
+        n_commit = i + 1;
+    }
+
+    let samples = if n_commit > 0 {
+        ms_to_samples(segments[n_commit - 1].end_ms).min(window_len)
+    } else if force && segments.is_empty() {
+        // Silence, or nothing that survived the filter: stop passing it to every later window
+        window_len.saturating_sub(ms_to_samples(SETTLE_MS))
+    } else {
+        0
+    };
+    Commit { segments: n_commit, samples }
+}
+
+/// One pass over a window. The committed text goes in as the prompt, after the user
+/// vocabulary, so words cut at the window start are continued rather than re-guessed.
+/// Also returns the language the window was decoded in. Setting `cancel` stops the pass.
+#[allow(clippy::too_many_arguments)]
+fn transcribe_window(
+    app: &AppHandle,
+    model_path: &std::path::Path,
+    opts: &LiveOptions,
+    window: &[f32],
+    vocabulary: Option<&str>,
+    committed: &str,
+    filter: &FilterRules,
+    cancel: &AtomicBool,
+) -> Result<(Vec<TranscriptSegment>, &'static str), String> {
+    let mut window = window.to_vec();
+    preprocess::process(&mut window, &opts.preprocess);
+
+    with_context(app, model_path, opts.use_gpu, || {}, |ctx| {
+        let mut state = ctx.create_state().map_err(|e| format!("Failed to create state: {}", e))?;
+        let detected = language::resolve(&mut state, &window, &opts.language, dictation_threads())?;
+        let mut params = dictation_params(opts.translate, &opts.decoding.options());
+        params.set_language(Some(detected.code));
+        params.set_no_context(true);
+        let start = committed
+            .char_indices()
+            .rev()
+            .nth(PROMPT_CHARS)
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        // Drop leading words until the tail fits, so it never pushes the vocabulary out
+        let mut tail = committed[start..].trim_start();
+        while let Ok(tokens) = ctx.tokenize(tail, tail.len() + 1) {
+            if tokens.len() <= TAIL_TOKENS {
+                break;
+            }
+            tail = match tail.split_once(' ') {
+                Some((_, rest)) => rest.trim_start(),
+                None => "",
+            };
+        }
+        let mut prompt = vocabulary.unwrap_or_default().to_string();
+        push_text(&mut prompt, tail);
+        if !prompt.is_empty() {
+            params.set_initial_prompt(&prompt);
+        }
+        inference::full_with_progress(&mut state, params, &window, cancel, |_| {})?;
+
+        let (segments, _) = postprocess::filter_segments(filter, transcript::read_segments(ctx, &state, false)?);
+        Ok((segments, detected.code))
+    })
+}
+
+/// Re-transcribe the uncommitted audio every `STEP_MS` and commit a leading segment
+/// once two consecutive passes agree on it and it has settled behind the live edge.
+/// When capture ends the rest of the take is transcribed once more and committed,
+/// unless `cancelled` was set: then the take is left alone.
+fn run(app: AppHandle, opts: LiveOptions, cancelled: &AtomicBool) -> Result<String, String> {
+    crate::TRANSCRIPTION_CANCELLED.store(false, Ordering::SeqCst);
+    let model_path = resolve_model_path(&app, &opts.model)?;
+    let vocabulary = match vocabulary::prompt(&app, opts.vocabulary_profile.as_deref()) {
+        Some(vocabulary) => with_context(&app, &model_path, opts.use_gpu, || {}, |ctx| Ok(vocabulary.fit(&app, ctx, TAIL_TOKENS)))?,
+        None => None,
+    };
+    let filter = postprocess::filter_rules(&app);
+    let audio = app.state::<AudioState>().inner().clone();
+
+    let mut committed = 0usize;
+    let mut stable = String::new();
+    let mut previous: Vec<String> = Vec::new();
+    let mut language = None;
+
+    loop {
+        std::thread::sleep(Duration::from_millis(STEP_MS));
+        if cancelled.load(Ordering::SeqCst) {
+            return Err(inference::CANCELLED.to_string());
+        }
+        if !*audio.is_recording.lock().unwrap() {
+            break;
+        }
+        // Halted at the length cap or paused: wait for the take to be stopped or resumed,
+        // the samples stay in place until `stop_recording` has read them
+        if !audio.capturing.load(Ordering::SeqCst) || audio.is_paused.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        let window: Vec<f32> = {
+            let buf = audio.samples.lock().unwrap();
+            if samples_to_ms(buf.len().saturating_sub(committed)) < MIN_WINDOW_MS {
+                continue;
+            }
+            buf[committed..].to_vec()
+        };
+        let (segments, detected) = transcribe_window(&app, &model_path, &opts, &window, vocabulary.as_deref(), &stable, &filter, cancelled)?;
+        language = Some(detected);
+
+        let commit = decide_commit(&segments, &previous, window.len());
+        for seg in &segments[..commit.segments] {
+            push_text(&mut stable, &seg.text);
+        }
+        committed += commit.samples;
+        previous = segments[commit.segments..].iter().map(|s| s.text.clone()).collect();
+
+        let mut tentative = String::new();
+        for text in &previous {
+            push_text(&mut tentative, text);
+        }
+        let _ = app.emit("partial-transcript", PartialTranscript { stable: stable.clone(), tentative, is_final: false });
+    }
+
+    // The samples may already belong to the next take
+    if cancelled.load(Ordering::SeqCst) {
+        return Err(inference::CANCELLED.to_string());
+    }
+    // Final pass over whatever was not committed yet; the take is done with after this.
+    // `cancel_transcription` stops it, the user is waiting on this one.
+    let tail = {
+        let mut buf = audio.samples.lock().unwrap();
+        let tail = buf.get(committed..).map(|t| t.to_vec()).unwrap_or_default();
+        buf.clear();
+        tail
+    };
+    if samples_to_ms(tail.len()) >= 100 {
+        let (segments, detected) = transcribe_window(&app, &model_path, &opts, &tail, vocabulary.as_deref(), &stable, &filter, &crate::TRANSCRIPTION_CANCELLED)?;
+        for seg in segments {
+            push_text(&mut stable, &seg.text);
+        }
+        language = Some(detected);
+    }
+
+    let mut text = stable;
+    if opts.spoken_commands {
+        // Translations always come out in English
+        let language = if opts.translate { "en" } else { language.unwrap_or("en") };
+        text = postprocess::spoken_commands(&app).apply(&text, language);
+    }
+    let text = postprocess::rule_set(&app).apply(&text);
+    let _ = app.emit("partial-transcript", PartialTranscript { stable: text.clone(), tentative: String::new(), is_final: true });
+    Ok(text)
+}
+
+pub(crate) fn spawn(app: AppHandle, opts: LiveOptions) -> LiveSession {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let flag = cancelled.clone();
+    let handle = std::thread::spawn(move || run(app, opts, &flag));
+    LiveSession { handle, cancelled }
+}
+
+/// Stop the session of an earlier take that was never finished and wait for it to exit,
+/// so it cannot clear the samples of the next take.
+pub(crate) async fn cancel(app: &AppHandle) {
+    let session = app.state::<AudioState>().live.lock().unwrap().take();
+    if let Some(session) = session {
+        session.cancelled.store(true, Ordering::SeqCst);
+        let _ = tauri::async_runtime::spawn_blocking(move || session.handle.join()).await;
+    }
+}
+
+/// Wait for the live session of the last take to commit its tail and return the full text.
+pub(crate) async fn finish(app: &AppHandle) -> Option<Result<String, String>> {
+    let session = app.state::<AudioState>().live.lock().unwrap().take()?;
+    let result = tauri::async_runtime::spawn_blocking(move || session.handle.join())
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|joined| joined.map_err(|_| "Live transcription panicked".to_string()))
+        .and_then(|result| result);
+    Some(result)
+}
+
+/// Final text of a take recorded with `live` options, once `stop_recording` has returned.
+#[tauri::command]
+pub async fn finish_live_transcription(app: AppHandle) -> Result<String, String> {
+    finish(&app)
+        .await
+        .unwrap_or_else(|| Err("No live transcription running".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_ms: u64, end_ms: u64, text: &str) -> TranscriptSegment {
+        TranscriptSegment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+            avg_logprob: 0.0,
+            no_speech_prob: None,
+            speaker: None,
+            words: Vec::new(),
+        }
+    }
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn agreed_settled_segments_are_committed() {
+        let segments = [segment(0, 2000, "one"), segment(2000, 4000, "two"), segment(4000, 5500, "three")];
+        let commit = decide_commit(&segments, &texts(&["one", "two", "three"]), ms_to_samples(6000));
+        // "three" ends only 500 ms before the live edge
+        assert_eq!(commit, Commit { segments: 2, samples: ms_to_samples(4000) });
+    }
+
+    #[test]
+    fn disagreement_stops_the_commit() {
+        let segments = [segment(0, 2000, "one"), segment(2000, 4000, "two")];
+        let commit = decide_commit(&segments, &texts(&["one", "too"]), ms_to_samples(8000));
+        assert_eq!(commit, Commit { segments: 1, samples: ms_to_samples(2000) });
+        let first_pass = decide_commit(&segments, &[], ms_to_samples(8000));
+        assert_eq!(first_pass, Commit { segments: 0, samples: 0 });
+    }
+
+    #[test]
+    fn long_windows_commit_settled_segments_without_agreement() {
+        let window = ms_to_samples(MAX_WINDOW_MS + 1000);
+        let segments = [segment(0, 10_000, "one"), segment(10_000, MAX_WINDOW_MS, "two")];
+        let commit = decide_commit(&segments, &[], window);
+        // "two" ends 1 s before the edge, inside the settle margin
+        assert_eq!(commit, Commit { segments: 1, samples: ms_to_samples(10_000) });
+    }
+
+    #[test]
+    fn long_silent_windows_are_skipped_up_to_the_settle_margin() {
+        let window = ms_to_samples(MAX_WINDOW_MS + 1000);
+        let commit = decide_commit(&[], &[], window);
+        assert_eq!(commit, Commit { segments: 0, samples: window - ms_to_samples(SETTLE_MS) });
+        // Short silences stay in the window
+        assert_eq!(decide_commit(&[], &[], ms_to_samples(5000)), Commit { segments: 0, samples: 0 });
+    }
+}
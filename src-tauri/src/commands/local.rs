@@ -1,9 +1,58 @@
-use whisper_rs::{WhisperContext, FullParams, SamplingStrategy, WhisperContextParameters};
+use whisper_rs::{WhisperContext, FullParams, WhisperContextParameters};
 use tauri::{AppHandle, Runtime, Manager, Emitter};
-use hound;
-use std::process::Command;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
 use serde::Serialize;
+use crate::chunked::{self, ChunkOptions};
+use crate::dsp::{preprocess::{self, PreprocessOptions}, SampleStream};
+use crate::inference;
+use crate::media;
+use crate::postprocess;
+use crate::transcript::{self, FilteredSegment, Transcript, TranscriptSegment};
+use super::decoding::{Decoding, DecodingOptions};
+use super::language::{self, DetectedLanguage, LanguageOptions};
+use super::vocabulary;
+
+/// Decoding settings of one transcription request, shared by all its Whisper runs
+pub(crate) struct RunOptions {
+    pub translate: bool,
+    pub language: LanguageOptions,
+    pub decoding: DecodingOptions,
+    /// User vocabulary, see `vocabulary::prompt`
+    pub vocabulary: Option<vocabulary::Vocabulary>,
+}
+
+/// A finished run, before the request details are added
+struct Decoded {
+    text: String,
+    segments: Vec<TranscriptSegment>,
+    filtered: Vec<FilteredSegment>,
+    language: DetectedLanguage,
+}
+
+/// With `spoken_commands` the phrase table of the output language is applied to the text;
+/// segments keep what was said, a command can undo text across them.
+fn into_transcript<R: Runtime>(app: &AppHandle<R>, Decoded { text, segments, filtered, language }: Decoded, model: String, use_gpu: bool, started: Instant, spoken_commands: bool, translate: bool) -> Transcript {
+    let text = if spoken_commands {
+        // Translations always come out in English
+        postprocess::spoken_commands(app).apply(&text, if translate { "en" } else { language.code })
+    } else {
+        text
+    };
+    let mut transcript = Transcript {
+        text,
+        segments,
+        filtered,
+        language,
+        model,
+        backend: transcript::backend_name(use_gpu),
+        processing_ms: 0,
+    };
+    transcript.apply_rules(&postprocess::rule_set(app));
+    transcript.processing_ms = started.elapsed().as_millis() as u64;
+    transcript
+}
 
 #[derive(Clone, Serialize)]
 pub struct TranscribeProgress {
@@ -13,73 +62,6 @@ pub struct TranscribeProgress {
     pub status: String,
 }
 
-/// Convert any media file to 16kHz mono WAV using ffmpeg
-fn convert_to_wav(input_path: &str, output_path: &str) -> Result<(), String> {
-    // First verify the input file exists
-    let input = Path::new(input_path);
-    if !input.exists() {
-        return Err(format!("Input file not found: {}", input_path));
-    }
-
-    let status = Command::new("ffmpeg")
-        .arg("-y")           // Overwrite output
-        .arg("-i")
-        .arg(input_path)     // Input path as single arg (handles spaces)
-        .arg("-vn")          // No video
-        .arg("-ac").arg("1") // Mono
-        .arg("-ar").arg("16000") // 16kHz sample rate
-        .arg("-acodec").arg("pcm_s16le") // 16-bit PCM
-        .arg("-f").arg("wav")
-        .arg(output_path)    // Output path as single arg
-        .output()
-        .map_err(|e| format!("Failed to run ffmpeg: {}. Is ffmpeg installed?", e))?;
-
-    if !status.status.success() {
-        let stderr = String::from_utf8_lossy(&status.stderr);
-        return Err(format!("ffmpeg conversion failed: {}", stderr));
-    }
-
-    // Verify output was created
-    if !Path::new(output_path).exists() {
-        return Err("ffmpeg completed but output file was not created".to_string());
-    }
-
-    Ok(())
-}
-
-/// Check if file needs conversion (not a WAV with correct format)
-fn needs_conversion(path: &str) -> bool {
-    let ext = Path::new(path)
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e.to_lowercase())
-        .unwrap_or_default();
-
-    // Video files always need conversion
-    if matches!(ext.as_str(), "mp4" | "mkv" | "webm" | "mov" | "avi" | "m4v") {
-        return true;
-    }
-
-    // Non-WAV audio files need conversion
-    if matches!(ext.as_str(), "mp3" | "m4a" | "flac" | "ogg" | "opus" | "aac" | "wma") {
-        return true;
-    }
-
-    // For WAV files, check if format is already correct
-    if ext == "wav" {
-        if let Ok(reader) = hound::WavReader::open(path) {
-            let spec = reader.spec();
-            // Already 16kHz mono 16-bit? No conversion needed
-            if spec.sample_rate == 16000 && spec.channels == 1 && spec.bits_per_sample == 16 {
-                return false;
-            }
-        }
-    }
-
-    // Anything else needs conversion
-    true
-}
-
 fn emit_progress<R: Runtime>(app: &AppHandle<R>, percentage: u32, processed_ms: u64, total_ms: u64, status: &str) {
     let _ = app.emit("transcribe-progress", TranscribeProgress {
         percentage,
@@ -89,120 +71,130 @@ fn emit_progress<R: Runtime>(app: &AppHandle<R>, percentage: u32, processed_ms:
     });
 }
 
-#[tauri::command]
-pub async fn transcribe_local<R: Runtime>(app: AppHandle<R>, path: String, model: String, _use_gpu: bool, translate: bool) -> Result<String, String> {
-    emit_progress(&app, 0, 0, 0, "loading");
-    
+pub(crate) fn resolve_model_path<R: Runtime>(app: &AppHandle<R>, model: &str) -> Result<PathBuf, String> {
     let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
     let model_path = app_data_dir.join("models").join(format!("ggml-{}.bin", model.to_lowercase()));
-    
+
     if !model_path.exists() {
         return Err("Model not found. Please download it first.".to_string());
     }
+    Ok(model_path)
+}
 
-    // Convert media if needed
-    let wav_path: String;
-    let temp_wav: Option<String>;
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn transcribe_local<R: Runtime>(app: AppHandle<R>, path: String, model: String, _use_gpu: bool, translate: bool, preprocess: Option<PreprocessOptions>, language: Option<LanguageOptions>, decoding: Option<Decoding>, spoken_commands: Option<bool>, stream: Option<usize>, start_ms: Option<u64>, end_ms: Option<u64>, vocabulary_profile: Option<String>) -> Result<Transcript, String> {
+    let started = Instant::now();
+    let range = media::TimeRange::new(start_ms, end_ms)?;
+    crate::TRANSCRIPTION_CANCELLED.store(false, Ordering::SeqCst);
+    emit_progress(&app, 0, 0, 0, "loading");
     
-    if needs_conversion(&path) {
-        emit_progress(&app, 5, 0, 0, "converting");
-        let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(format!("whisper_temp_{}.wav", std::process::id()));
-        wav_path = temp_file.to_string_lossy().to_string();
-        convert_to_wav(&path, &wav_path)?;
-        temp_wav = Some(wav_path.clone());
-    } else {
-        wav_path = path.clone();
-        temp_wav = None;
+    let model_path = resolve_model_path(&app, &model)?;
+
+    emit_progress(&app, 5, 0, 0, "loading_audio");
+
+    // Read as 16kHz mono while transcribing, converted by ffmpeg first only if nothing else can decode it
+    let result = media::open(&path, stream, range).and_then(|mut opened| {
+        let run = RunOptions {
+            translate,
+            language: language.unwrap_or_default().or_track_language(opened.track.language_code),
+            decoding: decoding.unwrap_or_default().options(),
+            vocabulary: vocabulary::prompt(&app, vocabulary_profile.as_deref()),
+        };
+        transcribe_file(&app, opened.audio.as_mut(), &model_path, _use_gpu, &run, &preprocess.unwrap_or_default(), range.start_ms)
+    });
+
+    // A failed or cancelled run reports through its error, not as complete
+    if result.is_ok() {
+        emit_progress(&app, 100, 0, 0, "complete");
     }
+    result.map(|decoded| into_transcript(&app, decoded, model, _use_gpu, started, spoken_commands.unwrap_or(false), translate))
+}
 
-    // We will emit loading_model inside transcribe_wav if needed
-    let result = transcribe_wav(&app, &wav_path, &model_path, _use_gpu, translate).await;
+/// Transcribe the take captured by `start_recording` straight from memory.
+/// The buffer is consumed; use the archive WAV if the audio is needed again.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn transcribe_recording<R: Runtime>(app: AppHandle<R>, model: String, use_gpu: bool, translate: bool, preprocess: Option<PreprocessOptions>, language: Option<LanguageOptions>, decoding: Option<Decoding>, spoken_commands: Option<bool>, vocabulary_profile: Option<String>) -> Result<Transcript, String> {
+    let started = Instant::now();
+    crate::TRANSCRIPTION_CANCELLED.store(false, Ordering::SeqCst);
+    emit_progress(&app, 0, 0, 0, "loading");
+
+    let model_path = resolve_model_path(&app, &model)?;
 
-    // Clean up temp file
-    if let Some(temp) = temp_wav {
-        let _ = std::fs::remove_file(&temp);
+    let (mut samples, mut them) = {
+        let audio_state = app.state::<crate::state::AudioState>();
+        let mut buf = audio_state.samples.lock().unwrap();
+        let mut them = audio_state.them.lock().unwrap();
+        (std::mem::take(&mut *buf), std::mem::take(&mut *them))
+    };
+    if samples.is_empty() {
+        return Err("No recorded audio to transcribe".to_string());
     }
+    let preprocess = preprocess.unwrap_or_default();
+    preprocess::process(&mut samples, &preprocess);
+    let run = RunOptions {
+        translate,
+        language: language.unwrap_or_default(),
+        decoding: decoding.unwrap_or_default().options(),
+        vocabulary: vocabulary::prompt(&app, vocabulary_profile.as_deref()),
+    };
+
+    let result = if them.is_empty() {
+        transcribe_samples(&app, &samples, &model_path, use_gpu, &run, (20, 90))
+    } else {
+        preprocess::process(&mut them, &preprocess);
+        transcribe_conversation(&app, &samples, &them, &model_path, use_gpu, &run)
+    };
 
-    emit_progress(&app, 100, 0, 0, "complete");
-    result
+    // A failed or cancelled run reports through its error, not as complete
+    if result.is_ok() {
+        emit_progress(&app, 100, 0, 0, "complete");
+    }
+    result.map(|decoded| into_transcript(&app, decoded, model, use_gpu, started, spoken_commands.unwrap_or(false), translate))
 }
 
-async fn transcribe_wav<R: Runtime>(
+/// Run `f` with the cached context for the model, loading it first if another model
+/// (or the same model on another backend) is cached. `on_load` runs only when loading.
+pub(crate) fn with_context<R: Runtime, T>(
     app: &AppHandle<R>,
-    wav_path: &str,
-    model_path: &std::path::Path,
+    model_path: &Path,
     _use_gpu: bool,
-    translate: bool,
-) -> Result<String, String> {
+    on_load: impl FnOnce(),
+    f: impl FnOnce(&WhisperContext) -> Result<T, String>,
+) -> Result<T, String> {
     let model_state = app.state::<crate::state::WhisperModelState>();
     let model_key = format!("{}-gpu:{}", model_path.to_string_lossy(), _use_gpu);
 
     let mut ctx_guard = model_state.context.lock().unwrap();
 
     if ctx_guard.as_ref().map(|(k, _)| k) != Some(&model_key) {
-        emit_progress(app, 10, 0, 0, "loading_model");
+        on_load();
+        // Only changed in GPU builds
+        #[allow(unused_mut)]
         let mut params = WhisperContextParameters::default();
         #[cfg(any(feature = "cuda", feature = "vulkan", feature = "rocm"))]
         {
             params.use_gpu(_use_gpu);
         }
-        let ctx = WhisperContext::new_with_params(&model_path.to_string_lossy(), params)
+        let ctx = WhisperContext::new_with_params(model_path, params)
             .map_err(|e| format!("Failed to load model: {}", e))?;
         *ctx_guard = Some((model_key, ctx));
     }
-    let ctx = &ctx_guard.as_ref().unwrap().1;
-
-    let mut state = ctx.create_state().map_err(|e| format!("Failed to create state: {}", e))?;
-
-    emit_progress(app, 15, 0, 0, "loading_audio");
-
-    // Audio processing - now always 16kHz mono from ffmpeg
-    let mut reader = hound::WavReader::open(wav_path)
-        .map_err(|e| format!("Failed to open wav file: {}", e))?;
-    let spec = reader.spec();
-
-    // Optimize: Read directly to f32 to avoid holding raw i16 vector in memory
-    let mut samples: Vec<f32> = reader.samples::<i16>()
-        .map(|s| s.unwrap_or(0) as f32 / 32768.0)
-        .collect();
-    
-    // Stereo to Mono if still needed
-    if spec.channels == 2 {
-        samples = samples.chunks(2).map(|chunk| (chunk[0] + chunk[1]) / 2.0).collect();
-    } else if spec.channels > 2 {
-        return Err("Unsupported channel count".to_string());
-    }
-
-    // Resample to 16kHz if necessary
-    if spec.sample_rate != 16000 {
-        let ratio = spec.sample_rate as f32 / 16000.0;
-        let new_len = (samples.len() as f32 / ratio) as usize;
-        let mut new_samples = Vec::with_capacity(new_len);
-        
-        for i in 0..new_len {
-            let old_idx_f = i as f32 * ratio;
-            let idx_floor = old_idx_f.floor() as usize;
-            let idx_ceil = (idx_floor + 1).min(samples.len() - 1);
-            let t = old_idx_f - idx_floor as f32;
-            let val = samples[idx_floor] * (1.0 - t) + samples[idx_ceil] * t;
-            new_samples.push(val);
-        }
-        samples = new_samples;
-    }
-
-    // Calculate audio duration
-    let total_duration_ms = (samples.len() as u64 * 1000) / 16000;
+    f(&ctx_guard.as_ref().unwrap().1)
+}
 
-    emit_progress(app, 20, 0, total_duration_ms, "transcribing");
+/// Limit threads to prevent system freeze
+pub(crate) fn dictation_threads() -> usize {
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+    if threads > 2 { threads - 2 } else { 1 }
+}
 
-    // Run inference
-    let mut wparams = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+/// Decoding parameters shared by dictation and live transcription.
+pub(crate) fn dictation_params(translate: bool, decoding: &DecodingOptions) -> FullParams<'static, 'static> {
+    let mut wparams = decoding.params();
 
-    // Limit threads to prevent system freeze
-    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
-    let n_threads = if threads > 2 { threads - 2 } else { 1 };
-    wparams.set_n_threads(n_threads as i32);
+    wparams.set_n_threads(dictation_threads() as i32);
 
     wparams.set_language(Some("auto"));
     wparams.set_translate(translate);
@@ -214,7 +206,7 @@ async fn transcribe_wav<R: Runtime>(
     // CRITICAL: Suppress non-speech tokens to prevent [Music], [BLANK_AUDIO] hallucinations
     // This forces Whisper to actually transcribe/translate instead of labeling as music
     wparams.set_suppress_blank(true);
-    wparams.set_suppress_non_speech_tokens(true);
+    wparams.set_suppress_nst(true);
     
     // Lower the no_speech threshold for translation - foreign languages can have different audio characteristics
     // that might be incorrectly classified as "no speech"
@@ -224,73 +216,153 @@ async fn transcribe_wav<R: Runtime>(
     } else {
         wparams.set_no_speech_thold(0.6);
         wparams.set_logprob_thold(-1.0);
-        // Only use prompt when NOT translating
-        let prompt = "Hello, welcome to the transcription. This is a clear English text.";
-        wparams.set_initial_prompt(prompt);
     }
+    decoding.apply_fallback(&mut wparams);
+    wparams
+}
 
-    // Run transcription (progress callback removed - was causing crashes)
-    state.full(wparams, &samples).map_err(|e| format!("Failed to run model: {}", e))?;
+/// Run Whisper over 16kHz mono samples with the cached model context and filter its
+/// segments for hallucinations. Progress is reported within `span` percent.
+fn transcribe_samples<R: Runtime>(
+    app: &AppHandle<R>,
+    samples: &[f32],
+    model_path: &std::path::Path,
+    _use_gpu: bool,
+    run: &RunOptions,
+    span: (u32, u32),
+) -> Result<Decoded, String> {
+    // Calculate audio duration
+    let total_duration_ms = (samples.len() as u64 * 1000) / 16000;
 
-    emit_progress(app, 90, total_duration_ms, total_duration_ms, "finishing");
+    with_context(app, model_path, _use_gpu, || emit_progress(app, 10, 0, 0, "loading_model"), |ctx| {
+        let mut state = ctx.create_state().map_err(|e| format!("Failed to create state: {}", e))?;
 
-    let num_segments = state.full_n_segments().map_err(|e| format!("Failed to get segments: {}", e))?;
-    println!("[DEBUG] Number of segments: {}", num_segments);
-    
-    let mut text = String::new();
-    
-    for i in 0..num_segments {
-        let segment = state.full_get_segment_text(i).map_err(|e| format!("Failed to get segment text: {}", e))?;
-        println!("[DEBUG] Segment {}: {:?}", i, segment);
-        text.push_str(&segment);
-        text.push(' ');
-    }
+        let detected = language::resolve(&mut state, samples, &run.language, dictation_threads())?;
 
-    println!("[DEBUG] Raw text before filtering: {:?}", text);
-    let mut trimmed = text.trim().to_string();
+        emit_progress(app, span.0, 0, total_duration_ms, "transcribing");
 
-    // Clean up annotations like [Music], [Applause], etc.
-    let annotations = ["[Music]", "[music]", "[Applause]", "[applause]", "[Laughter]", "[laughter]"];
-    for annotation in annotations {
-        trimmed = trimmed.replace(annotation, "").trim().to_string();
-    }
+        let mut params = dictation_params(run.translate, &run.decoding);
+        params.set_language(Some(detected.code));
+        if let Some(prompt) = run.vocabulary.as_ref().and_then(|v| v.fit(app, ctx, 0)) {
+            params.set_initial_prompt(&prompt);
+        }
 
-    println!("[DEBUG] After annotation cleanup: {:?}", trimmed);
+        inference::full_with_progress(&mut state, params, samples, &crate::TRANSCRIPTION_CANCELLED, |percent| {
+            let percent = percent.clamp(0, 100) as u32;
+            let percentage = span.0 + (span.1 - span.0) * percent / 100;
+            emit_progress(app, percentage, total_duration_ms * percent as u64 / 100, total_duration_ms, "transcribing");
+        })?;
+
+        emit_progress(app, span.1, total_duration_ms, total_duration_ms, "finishing");
+
+        let segments = transcript::read_segments(ctx, &state, false)?;
+        println!("[DEBUG] Number of segments: {}", segments.len());
+        let (segments, filtered) = postprocess::filter_segments(&postprocess::filter_rules(app), segments);
+        Ok(Decoded {
+            text: transcript::join_text(&segments),
+            segments,
+            filtered,
+            language: detected,
+        })
+    })
+}
 
-    // Hallucination Filter - only for pure annotation segments
-    let lower = trimmed.to_lowercase();
-    if trimmed.is_empty() {
-        println!("[DEBUG] Result is empty after cleanup");
-        return Ok("".to_string());
-    }
-    
-    // Only filter if ENTIRE result is just an annotation
-    if (lower.starts_with('(') || lower.starts_with('[')) && (lower.ends_with(')') || lower.ends_with(']')) {
-        if lower.contains("speaking") || lower.contains("foreign") || lower.contains("silence") || lower.contains("music") || lower.contains("appla") {
-             return Ok("".to_string());
-        }
-    }
+/// `transcribe_samples` for a file too long to load at once, read and decoded a chunk at a time.
+fn transcribe_file<R: Runtime>(
+    app: &AppHandle<R>,
+    stream: &mut dyn SampleStream,
+    model_path: &std::path::Path,
+    use_gpu: bool,
+    run: &RunOptions,
+    preprocess: &PreprocessOptions,
+    start_ms: u64,
+) -> Result<Decoded, String> {
+    let total_ms = stream.duration_ms();
+
+    with_context(app, model_path, use_gpu, || emit_progress(app, 10, 0, 0, "loading_model"), |ctx| {
+        let prompt = run.vocabulary.as_ref().and_then(|v| v.fit(app, ctx, 0));
+        emit_progress(app, 20, 0, total_ms, "transcribing");
+
+        let filter = postprocess::filter_rules(app);
+        let options = ChunkOptions {
+            language: &run.language,
+            preprocess,
+            prompt: prompt.as_deref(),
+            filter: &filter,
+            threads: dictation_threads(),
+            words: false,
+            start_ms,
+        };
+        let chunked = chunked::transcribe_stream(
+            ctx,
+            stream,
+            &options,
+            &crate::TRANSCRIPTION_CANCELLED,
+            || dictation_params(run.translate, &run.decoding),
+            |processed_ms| {
+                let processed_ms = processed_ms.min(total_ms);
+                let percentage = 20 + (70 * processed_ms / total_ms.max(1)) as u32;
+                emit_progress(app, percentage, processed_ms, total_ms, "transcribing");
+            },
+            |_| Ok(()),
+        )?;
+
+        emit_progress(app, 90, total_ms, total_ms, "finishing");
+        Ok(Decoded {
+            text: transcript::join_text(&chunked.segments),
+            segments: chunked.segments,
+            filtered: chunked.filtered,
+            language: chunked.language,
+        })
+    })
+}
 
-    // Hallucination filter - only very specific patterns that are definitely not real transcriptions
-    // Be VERY careful here - we don't want to filter valid content
-    let hallucinations = [
-        "Subscribe to my channel",
-        "Amara.org",
-        "Subtitles by the",
-        "Transcribed by",
-        "This is a clear English text",
-        "Hello, welcome to the transcription",
-        "Thank you for watching",
-    ];
-
-    // Only filter if the ENTIRE text is just the hallucination (not if it contains it)
-    let lower = trimmed.to_lowercase();
-    for h in hallucinations {
-        if lower == h.to_lowercase() {
-            println!("[DEBUG] Filtered as hallucination: {}", h);
-            return Ok("".to_string());
+/// Transcribe the two tracks of a separate-channel take and interleave them by time
+/// as a "Me:" / "Them:" dialogue. Each track detects its own language; the mic's is reported.
+fn transcribe_conversation<R: Runtime>(
+    app: &AppHandle<R>,
+    me: &[f32],
+    them: &[f32],
+    model_path: &std::path::Path,
+    use_gpu: bool,
+    run: &RunOptions,
+) -> Result<Decoded, String> {
+    let mut segments: Vec<TranscriptSegment> = Vec::new();
+    let mut filtered: Vec<FilteredSegment> = Vec::new();
+    let mut detected = None;
+    // Each track fills its half of the progress bar
+    for (speaker, samples, span) in [("Me", me, (20, 55)), ("Them", them, (55, 90))] {
+        let track = transcribe_samples(app, samples, model_path, use_gpu, run, span)?;
+        detected.get_or_insert(track.language);
+        segments.extend(track.segments.into_iter().map(|segment| TranscriptSegment {
+            speaker: Some(speaker.to_string()),
+            ..segment
+        }));
+        filtered.extend(track.filtered);
+    }
+    segments.sort_by_key(|segment| segment.start_ms);
+    filtered.sort_by_key(|segment| segment.start_ms);
+
+    // Consecutive segments of the same speaker form one turn
+    let mut dialogue = String::new();
+    let mut current: Option<&str> = None;
+    for segment in &segments {
+        let speaker = match &segment.speaker {
+            Some(speaker) => speaker.as_str(),
+            None => continue,
+        };
+        if current == Some(speaker) {
+            dialogue.push(' ');
+        } else {
+            if current.is_some() {
+                dialogue.push('\n');
+            }
+            dialogue.push_str(speaker);
+            dialogue.push_str(": ");
+            current = Some(speaker);
         }
+        dialogue.push_str(&segment.text);
     }
-
-    Ok(trimmed)
+    let language = detected.ok_or("No audio tracks to transcribe")?;
+    Ok(Decoded { text: dialogue, segments, filtered, language })
 }
@@ -0,0 +1,259 @@
+// System audio ("what the speakers play") as a recording source.
+// PulseAudio/PipeWire expose it as monitor sources that cpal's ALSA host can't see,
+// so those are read through `parec`. On Windows cpal opens an output device in
+// WASAPI loopback mode. Virtual devices like BlackHole are plain inputs already.
+
+use std::sync::atomic::Ordering;
+use serde::Deserialize;
+use crate::dsp::{self, resample::Resampler, WHISPER_SAMPLE_RATE};
+use crate::state::{AudioState, CaptureDevice, CaptureSource, LoopbackMode, LoopbackStream, SendStream};
+use super::audio::{build_any_stream, feed_take, find_input_device, InputConfigInfo, InputDeviceInfo};
+
+/// Second source recorded alongside the microphone
+#[derive(Clone, Deserialize)]
+pub struct LoopbackOptions {
+    pub device_id: String,
+    pub mode: LoopbackMode,
+}
+
+const ID_PREFIX: &str = "loopback:";
+
+/// Input devices that are really virtual loopback cables
+const VIRTUAL_DEVICE_NAMES: &[&str] = &["blackhole", "soundflower", "loopback", "vb-audio", "cable output", "monitor of"];
+
+pub fn is_loopback_id(id: &str) -> bool {
+    id.starts_with(ID_PREFIX)
+}
+
+/// Whether a regular input device is a virtual loopback device
+pub fn is_virtual_loopback(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    VIRTUAL_DEVICE_NAMES.iter().any(|n| lower.contains(n))
+}
+
+/// Monitor sources of every PulseAudio/PipeWire sink, as (source name, description)
+#[cfg(target_os = "linux")]
+fn monitor_sources() -> Vec<(String, String)> {
+    // Field labels are translated, so ask for the C locale
+    let output = match std::process::Command::new("pactl")
+        .args(["list", "sources"])
+        .env("LC_ALL", "C")
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+
+    let mut sources = Vec::new();
+    let mut name: Option<String> = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("Name: ") {
+            name = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("Description: ") {
+            if let Some(name) = name.take() {
+                if name.ends_with(".monitor") {
+                    sources.push((name, value.to_string()));
+                }
+            }
+        }
+    }
+    sources
+}
+
+/// System-audio sources for the device picker
+pub fn list_sources() -> Vec<InputDeviceInfo> {
+    let mut result = Vec::new();
+
+    #[cfg(target_os = "linux")]
+    for (name, description) in monitor_sources() {
+        result.push(InputDeviceInfo {
+            id: format!("{}pulse:{}", ID_PREFIX, name),
+            name: description,
+            is_default: false,
+            is_loopback: true,
+            // parec converts to what we ask for
+            configs: vec![InputConfigInfo {
+                channels: 1,
+                min_sample_rate: WHISPER_SAMPLE_RATE,
+                max_sample_rate: WHISPER_SAMPLE_RATE,
+                sample_format: "f32".to_string(),
+            }],
+        });
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use cpal::traits::{DeviceTrait, HostTrait};
+        let host = cpal::default_host();
+        if let Ok(devices) = host.output_devices() {
+            for device in devices {
+                let name = match device.name() {
+                    Ok(name) => name,
+                    Err(_) => continue,
+                };
+                let configs = match device.supported_output_configs() {
+                    Ok(configs) => configs
+                        .map(|c| InputConfigInfo {
+                            channels: c.channels(),
+                            min_sample_rate: c.min_sample_rate().0,
+                            max_sample_rate: c.max_sample_rate().0,
+                            sample_format: c.sample_format().to_string(),
+                        })
+                        .collect(),
+                    Err(_) => continue,
+                };
+                result.push(InputDeviceInfo {
+                    id: format!("{}wasapi:{}", ID_PREFIX, name),
+                    name: format!("{} (system audio)", name),
+                    is_default: false,
+                    is_loopback: true,
+                    configs,
+                });
+            }
+        }
+    }
+
+    result
+}
+
+/// Where system-audio samples go: straight into the take when it is the only source,
+/// through the mic aligner otherwise. Also drives the meter when there is no mic.
+fn system_sink(state: AudioState, primary: bool) -> impl FnMut(&[f32]) + Send + 'static {
+    move |samples: &[f32]| {
+        if primary {
+            let rms = if samples.is_empty() {
+                0.0
+            } else {
+                (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+            };
+            if let Ok(mut lvl) = state.current_level.lock() {
+                *lvl = rms;
+            }
+        }
+        if !state.capturing.load(Ordering::SeqCst) || state.is_paused.load(Ordering::SeqCst) {
+            return;
+        }
+        let peak = samples.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
+        if let Ok(mut max_guard) = state.max_amplitude.lock() {
+            if peak > *max_guard {
+                *max_guard = peak;
+            }
+        }
+        feed_take(&state, CaptureSource::System, samples);
+    }
+}
+
+/// Read a monitor source through `parec`, already converted to 16kHz mono f32.
+#[cfg(target_os = "linux")]
+fn open_parec(source: &str, mut sink: impl FnMut(&[f32]) + Send + 'static) -> Result<(LoopbackStream, CaptureDevice), String> {
+    use std::io::Read;
+
+    let mut child = std::process::Command::new("parec")
+        .arg(format!("--device={}", source))
+        .args(["--format=float32le", "--channels=1", "--latency-msec=50", "--raw"])
+        .arg(format!("--rate={}", WHISPER_SAMPLE_RATE))
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run parec: {}. Is pulseaudio-utils installed?", e))?;
+    let mut stdout = child.stdout.take().ok_or("parec has no output")?;
+
+    // Ends by itself when the child is killed and the pipe closes
+    std::thread::spawn(move || {
+        let mut bytes = [0u8; 4096];
+        let mut pending: Vec<u8> = Vec::new();
+        let mut samples: Vec<f32> = Vec::new();
+        while let Ok(n) = stdout.read(&mut bytes) {
+            if n == 0 {
+                break;
+            }
+            pending.extend_from_slice(&bytes[..n]);
+            let whole = pending.len() / 4 * 4;
+            samples.clear();
+            samples.extend(
+                pending[..whole]
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            );
+            pending.drain(..whole);
+            sink(&samples);
+        }
+    });
+
+    let device = CaptureDevice {
+        name: source.to_string(),
+        sample_rate: WHISPER_SAMPLE_RATE,
+        channels: 1,
+    };
+    Ok((LoopbackStream::Parec(child), device))
+}
+
+/// Downmix and resample a cpal capture before handing it to the sink.
+fn open_cpal(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    mut sink: impl FnMut(&[f32]) + Send + 'static,
+) -> Result<(LoopbackStream, CaptureDevice), String> {
+    use cpal::traits::{DeviceTrait, StreamTrait};
+
+    let info = CaptureDevice {
+        name: device.name().unwrap_or_else(|_| "unknown".to_string()),
+        sample_rate: config.sample_rate().0,
+        channels: config.channels(),
+    };
+    let channels = config.channels() as usize;
+    let mut resampler = Resampler::new(config.sample_rate().0, WHISPER_SAMPLE_RATE)?;
+    let mut mono: Vec<f32> = Vec::new();
+    let mut resampled: Vec<f32> = Vec::new();
+    let stream = build_any_stream(device, config, move |data: &[f32]| {
+        mono.clear();
+        resampled.clear();
+        dsp::downmix(data, channels, &mut mono);
+        resampler.process(&mono, &mut resampled);
+        sink(&resampled);
+    })?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok((LoopbackStream::Cpal(SendStream(stream)), info))
+}
+
+/// Start capturing system audio into the take.
+/// `primary` is set when there is no microphone stream for this take.
+pub fn open(state: &AudioState, id: &str, primary: bool) -> Result<(LoopbackStream, CaptureDevice), String> {
+    use cpal::traits::DeviceTrait;
+
+    let sink = system_sink(state.clone(), primary);
+    let host = cpal::default_host();
+
+    let source = match id.strip_prefix(ID_PREFIX) {
+        Some(source) => source,
+        None => {
+            // Virtual loopback devices are regular inputs
+            let device = find_input_device(&host, id)
+                .ok_or_else(|| format!("System audio device {} not found", id))?;
+            let config = device.default_input_config().map_err(|e| e.to_string())?;
+            return open_cpal(&device, &config, sink);
+        }
+    };
+
+    #[cfg(target_os = "linux")]
+    if let Some(monitor) = source.strip_prefix("pulse:") {
+        return open_parec(monitor, sink);
+    }
+
+    #[cfg(target_os = "windows")]
+    if let Some(wanted) = source.strip_prefix("wasapi:") {
+        use cpal::traits::HostTrait;
+        let device = host
+            .output_devices()
+            .map_err(|e| e.to_string())?
+            .find(|d| d.name().map(|n| n == wanted).unwrap_or(false))
+            .ok_or_else(|| format!("Output device {} not found", wanted))?;
+        // An input stream on an output device is a loopback capture in cpal's WASAPI host
+        let config = device.default_output_config().map_err(|e| e.to_string())?;
+        return open_cpal(&device, &config, sink);
+    }
+
+    let _ = (sink, source);
+    Err(format!("System audio source {} is not available on this platform", id))
+}
@@ -0,0 +1,9 @@
+// Media file inspection, so the UI can show what a file holds before transcribing it.
+
+use crate::media::probe::{self, MediaInfo};
+
+/// Container, streams and length of a file, and how its audio will be decoded
+#[tauri::command]
+pub async fn probe_media(path: String) -> Result<MediaInfo, String> {
+    probe::probe(&path)
+}
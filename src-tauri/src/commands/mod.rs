@@ -4,3 +4,12 @@ pub mod sounds;
 pub mod manager;
 pub mod local;
 pub mod srt;
+pub mod language;
+pub mod decoding;
+pub mod vocabulary;
+pub mod rules;
+pub mod filter;
+pub mod spoken;
+pub mod media;
+pub mod live;
+pub mod loopback;
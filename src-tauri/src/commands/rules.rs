@@ -0,0 +1,50 @@
+// Replacement rules as edited in settings, plus JSON import/export so they can be shared.
+
+use std::collections::HashSet;
+use tauri::{AppHandle, Runtime};
+use crate::postprocess::{self, rules::{ReplacementRule, RuleSet}};
+use crate::storage;
+
+/// Reject rules that would not compile and give every rule a unique id, keeping the order.
+fn prepare(mut rules: Vec<ReplacementRule>) -> Result<Vec<ReplacementRule>, String> {
+    RuleSet::compile(&rules)?;
+    let mut seen = HashSet::new();
+    for rule in &mut rules {
+        if rule.id.is_empty() || !seen.insert(rule.id.clone()) {
+            rule.id = storage::new_id("rule");
+            seen.insert(rule.id.clone());
+        }
+    }
+    Ok(rules)
+}
+
+#[tauri::command]
+pub fn list_replacement_rules<R: Runtime>(app: AppHandle<R>) -> Result<Vec<ReplacementRule>, String> {
+    postprocess::load_rules(&app)
+}
+
+/// Replace the whole list; its order is the order rules run in. Returns the saved rules.
+#[tauri::command]
+pub fn save_replacement_rules<R: Runtime>(app: AppHandle<R>, rules: Vec<ReplacementRule>) -> Result<Vec<ReplacementRule>, String> {
+    let rules = prepare(rules)?;
+    postprocess::save_rules(&app, &rules)?;
+    Ok(rules)
+}
+
+/// Read rules from a JSON file, either replacing the current ones or appended after them.
+#[tauri::command]
+pub fn import_replacement_rules<R: Runtime>(app: AppHandle<R>, path: String, replace: bool) -> Result<Vec<ReplacementRule>, String> {
+    let data = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let imported: Vec<ReplacementRule> = serde_json::from_str(&data).map_err(|e| format!("Not a rules file: {}", e))?;
+
+    let mut rules = if replace { Vec::new() } else { postprocess::load_rules(&app)? };
+    rules.extend(imported);
+    save_replacement_rules(app, rules)
+}
+
+#[tauri::command]
+pub fn export_replacement_rules<R: Runtime>(app: AppHandle<R>, path: String) -> Result<(), String> {
+    let rules = postprocess::load_rules(&app)?;
+    let data = serde_json::to_string_pretty(&rules).map_err(|e| e.to_string())?;
+    std::fs::write(&path, data).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
@@ -0,0 +1,35 @@
+// Spoken-command phrase tables, applied to dictation and live transcription.
+
+use tauri::{AppHandle, Runtime};
+use crate::postprocess::{self, spoken::SpokenCommands};
+
+#[tauri::command]
+pub fn get_spoken_commands<R: Runtime>(app: AppHandle<R>) -> Result<SpokenCommands, String> {
+    postprocess::load_spoken(&app)
+}
+
+/// Returns the saved tables, phrases lowercased and blank ones dropped.
+#[tauri::command]
+pub fn save_spoken_commands<R: Runtime>(app: AppHandle<R>, mut commands: SpokenCommands) -> Result<SpokenCommands, String> {
+    for table in commands.languages.values_mut() {
+        for command in table.iter_mut() {
+            command.phrase = command.phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        }
+        table.retain(|command| !command.phrase.is_empty());
+    }
+    commands.languages.retain(|code, table| !code.trim().is_empty() && !table.is_empty());
+    commands.ordinary_words = commands
+        .ordinary_words
+        .iter()
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    postprocess::save_spoken(&app, &commands)?;
+    Ok(commands)
+}
+
+/// Back to the built-in tables
+#[tauri::command]
+pub fn reset_spoken_commands<R: Runtime>(app: AppHandle<R>) -> Result<SpokenCommands, String> {
+    save_spoken_commands(app, SpokenCommands::default())
+}
@@ -1,11 +1,27 @@
-use whisper_rs::{WhisperContext, FullParams, SamplingStrategy, WhisperContextParameters, DtwParameters, DtwMode, DtwModelPreset};
+use whisper_rs::{FullParams, WhisperContext, WhisperContextParameters, DtwParameters, DtwMode, DtwModelPreset};
 use tauri::{AppHandle, Runtime, Manager, Emitter};
-use hound;
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::time::Instant;
 use serde::Serialize;
+use crate::chunked::{self, ChunkOptions};
+use crate::inference;
+use crate::media;
+use crate::postprocess;
+use crate::transcript::{self, Transcript};
+use super::decoding::{Decoding, DecodingOptions};
+use super::language::LanguageOptions;
+use super::vocabulary;
+
+#[derive(Clone, Serialize)]
+pub struct SrtResult {
+    pub path: String,
+    /// Segments come with word timings, SRT runs use token timestamps
+    pub transcript: Transcript,
+    /// Recorded take the subtitles were made from, when it was kept
+    pub archive_path: Option<String>,
+}
 
 #[derive(Clone, Serialize)]
 pub struct SrtProgress {
@@ -15,67 +31,55 @@ pub struct SrtProgress {
     pub status: String,
 }
 
-/// Convert any media file to 16kHz mono WAV using ffmpeg
-fn convert_to_wav(input_path: &str, output_path: &str) -> Result<(), String> {
-    let input = Path::new(input_path);
-    if !input.exists() {
-        return Err(format!("Input file not found: {}", input_path));
-    }
-
-    let status = Command::new("ffmpeg")
-        .arg("-y")
-        .arg("-i").arg(input_path)
-        .arg("-vn")
-        .arg("-ac").arg("1")
-        .arg("-ar").arg("16000")
-        .arg("-acodec").arg("pcm_s16le")
-        .arg("-f").arg("wav")
-        .arg(output_path)
-        .output()
-        .map_err(|e| format!("Failed to run ffmpeg: {}. Is ffmpeg installed?", e))?;
-
-    if !status.status.success() {
-        let stderr = String::from_utf8_lossy(&status.stderr);
-        return Err(format!("ffmpeg conversion failed: {}", stderr));
-    }
-
-    if !Path::new(output_path).exists() {
-        return Err("ffmpeg completed but output file was not created".to_string());
-    }
-
-    Ok(())
+// HH:MM:SS,mmm
+fn format_timestamp(ms: i64) -> String {
+    let hours = ms / 3600000;
+    let minutes = (ms % 3600000) / 60000;
+    let seconds = (ms % 60000) / 1000;
+    let millis = ms % 1000;
+    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, seconds, millis)
 }
 
-/// Check if file needs conversion
-fn needs_conversion(path: &str) -> bool {
-    let ext = Path::new(path)
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e.to_lowercase())
-        .unwrap_or_default();
+fn srt_entry(index: usize, start_ms: i64, end_ms: i64, text: &str) -> String {
+    format!("{}\n{} --> {}\n{}\n\n", index, format_timestamp(start_ms), format_timestamp(end_ms), text.trim())
+}
 
-    if matches!(ext.as_str(), "mp4" | "mkv" | "webm" | "mov" | "avi" | "m4v") {
-        return true;
-    }
-    if matches!(ext.as_str(), "mp3" | "m4a" | "flac" | "ogg" | "opus" | "aac" | "wma") {
-        return true;
-    }
-    if ext == "wav" {
-        if let Ok(reader) = hound::WavReader::open(path) {
-            let spec = reader.spec();
-            if spec.sample_rate == 16000 && spec.channels == 1 && spec.bits_per_sample == 16 {
-                return false;
-            }
-        }
+/// Decoding parameters tuned for subtitles: token timestamps for precise timing
+/// and lenient thresholds so quiet voices are caught.
+fn srt_params(translate: bool, decoding: &DecodingOptions, n_threads: i32) -> FullParams<'static, 'static> {
+    let mut whisper_params = decoding.params();
+    whisper_params.set_n_threads(n_threads);
+    whisper_params.set_translate(translate);
+    whisper_params.set_print_special(false);
+    whisper_params.set_print_progress(false);
+    whisper_params.set_print_realtime(false);
+    whisper_params.set_print_timestamps(false);
+    
+    // ENABLE token timestamps for precise millisecond timing
+    whisper_params.set_token_timestamps(true);
+    // Set maximum segment length to force more frequent updates (approx 60 tokens)
+    whisper_params.set_max_len(60); 
+    
+    // CRITICAL: Suppress non-speech tokens to prevent [Music], [BLANK_AUDIO] hallucinations
+    whisper_params.set_suppress_blank(true);
+    whisper_params.set_suppress_nst(true);
+    
+    // Lower thresholds for better low voice capture
+    // no_speech_thold: lower = more sensitive to quiet speech
+    // entropy_thold: higher = allow more uncertain segments (catches quiet voices)
+    if translate {
+        whisper_params.set_no_speech_thold(0.1); // Very lenient for translation
+        whisper_params.set_logprob_thold(-2.0);
+    } else {
+        whisper_params.set_no_speech_thold(0.3); // More lenient than before (was 0.6)
+        whisper_params.set_logprob_thold(-1.5);
     }
-    true
+    decoding.apply_fallback(&mut whisper_params);
+    whisper_params
 }
 
-
-
-
-
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn generate_srt<R: Runtime>(
     app: AppHandle<R>,
     audio_path: String,
@@ -84,7 +88,16 @@ pub async fn generate_srt<R: Runtime>(
     translate: bool,
     use_gpu: bool,
     duplicate_mode: String, // "overwrite" or "rename"
-) -> Result<String, String> {
+    preprocess: Option<crate::dsp::preprocess::PreprocessOptions>,
+    language: Option<LanguageOptions>,
+    decoding: Option<Decoding>,
+    stream: Option<usize>,
+    start_ms: Option<u64>,
+    end_ms: Option<u64>,
+    keep_archive: Option<bool>,
+    vocabulary_profile: Option<String>,
+) -> Result<SrtResult, String> {
+    let started = Instant::now();
     // Emit starting progress
     let _ = app.emit("srt-progress", SrtProgress {
         percentage: 0,
@@ -144,38 +157,13 @@ pub async fn generate_srt<R: Runtime>(
         }
         let _ = use_gpu;
         
-        let ctx = WhisperContext::new_with_params(&model_path.to_string_lossy(), params)
+        let ctx = WhisperContext::new_with_params(model_path, params)
             .map_err(|e| format!("Failed to load model: {}", e))?;
         *ctx_guard = Some((model_key, ctx));
     }
     
     let ctx = &ctx_guard.as_ref().unwrap().1;
 
-    let mut state = ctx.create_state().map_err(|e| format!("Failed to create state: {}", e))?;
-
-    // Emit model loaded
-    let _ = app.emit("srt-progress", SrtProgress {
-        percentage: 5,
-        processed_ms: 0,
-        total_ms: 0,
-        status: "converting".to_string(),
-    });
-
-    // Convert media if needed
-    let wav_path: String;
-    let temp_wav: Option<String>;
-    
-    if needs_conversion(&audio_path) {
-        let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(format!("whisper_srt_{}.wav", std::process::id()));
-        wav_path = temp_file.to_string_lossy().to_string();
-        convert_to_wav(&audio_path, &wav_path)?;
-        temp_wav = Some(wav_path.clone());
-    } else {
-        wav_path = audio_path.clone();
-        temp_wav = None;
-    }
-
     let _ = app.emit("srt-progress", SrtProgress {
         percentage: 10,
         processed_ms: 0,
@@ -183,63 +171,15 @@ pub async fn generate_srt<R: Runtime>(
         status: "loading_audio".to_string(),
     });
 
-    // Load and process audio
-    let mut reader = hound::WavReader::open(&wav_path)
-        .map_err(|e| {
-            // Clean up temp file on error
-            if let Some(ref temp) = temp_wav {
-                let _ = std::fs::remove_file(temp);
-            }
-            format!("Failed to open wav file: {}", e)
-        })?;
-    let spec = reader.spec();
-
-    // Optimize: Read directly to f32 to avoid holding raw i16 vector in memory
-    let mut samples: Vec<f32> = reader.samples::<i16>()
-        .map(|s| s.unwrap_or(0) as f32 / 32768.0)
-        .collect();
-    
-    // Stereo to Mono
-    if spec.channels == 2 {
-        samples = samples.chunks(2).map(|chunk| (chunk[0] + chunk[1]) / 2.0).collect();
-    } else if spec.channels > 2 {
-        return Err("Unsupported channel count".to_string());
-    }
-
-    // Resample to 16kHz if necessary
-    let sample_rate = spec.sample_rate;
-    if sample_rate != 16000 {
-        let ratio = sample_rate as f32 / 16000.0;
-        let new_len = (samples.len() as f32 / ratio) as usize;
-        let mut new_samples = Vec::with_capacity(new_len);
-        
-        for i in 0..new_len {
-            let old_idx_f = i as f32 * ratio;
-            let idx_floor = old_idx_f.floor() as usize;
-            let idx_ceil = (idx_floor + 1).min(samples.len() - 1);
-            let t = old_idx_f - idx_floor as f32;
-            let val = samples[idx_floor] * (1.0 - t) + samples[idx_ceil] * t;
-            new_samples.push(val);
-        }
-        samples = new_samples;
-    }
-
-    let total_duration_ms = (samples.len() as f64 / 16.0) as u64; // 16000 samples per second = 16 per ms
-
-    // Emit audio loaded
-    let _ = app.emit("srt-progress", SrtProgress {
-        percentage: 10,
-        processed_ms: 0,
-        total_ms: total_duration_ms,
-        status: "preprocessing".to_string(),
-    });
+    // Audio is decoded to 16kHz mono a chunk at a time, however long the file is
+    // Only the range is decoded; timestamps stay those of the whole file
+    let range = media::TimeRange::new(start_ms, end_ms)?;
+    let mut opened = media::open(&audio_path, stream, range)?;
+    let total_duration_ms = opened.audio.duration_ms();
 
     // Reset cancellation flag
-    crate::SRT_CANCELLED.store(false, std::sync::atomic::Ordering::SeqCst);
+    crate::TRANSCRIPTION_CANCELLED.store(false, std::sync::atomic::Ordering::SeqCst);
 
-    // Configure Whisper params for precise timestamps
-    let mut whisper_params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-    
     // Limit threads to prevent system freeze/overwhelm
     // Leave 2 cores free for OS/UI if possible
     let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
@@ -249,36 +189,10 @@ pub async fn generate_srt<R: Runtime>(
     } else { 
         1 
     };
-    whisper_params.set_n_threads(n_threads);
     println!("[SRT] Using {} threads (of {} available)", n_threads, threads);
 
-    whisper_params.set_language(Some("auto"));
-    whisper_params.set_translate(translate);
-    whisper_params.set_print_special(false);
-    whisper_params.set_print_progress(false);
-    whisper_params.set_print_realtime(false);
-    whisper_params.set_print_timestamps(false);
-    
-    // ENABLE token timestamps for precise millisecond timing
-    whisper_params.set_token_timestamps(true);
-    // Set maximum segment length to force more frequent updates (approx 60 tokens)
-    whisper_params.set_max_len(60); 
-    
-    // CRITICAL: Suppress non-speech tokens to prevent [Music], [BLANK_AUDIO] hallucinations
-    whisper_params.set_suppress_blank(true);
-    whisper_params.set_suppress_non_speech_tokens(true);
-    
-    // Lower thresholds for better low voice capture
-    // no_speech_thold: lower = more sensitive to quiet speech
-    // entropy_thold: higher = allow more uncertain segments (catches quiet voices)
-    if translate {
-        whisper_params.set_no_speech_thold(0.1); // Very lenient for translation
-        whisper_params.set_logprob_thold(-2.0);
-    } else {
-        whisper_params.set_no_speech_thold(0.3); // More lenient than before (was 0.6)
-        whisper_params.set_logprob_thold(-1.5);
-    }
-    // Do NOT set max_len - let Whisper create natural sentence-length segments
+    let decoding = decoding.unwrap_or_default().options();
+    let prompt = vocabulary::prompt(&app, vocabulary_profile.as_deref()).and_then(|v| v.fit(&app, ctx, 0));
 
     // Emit transcribing status
     let _ = app.emit("srt-progress", SrtProgress {
@@ -289,48 +203,10 @@ pub async fn generate_srt<R: Runtime>(
     });
 
     // Check for cancellation before transcription
-    if crate::SRT_CANCELLED.load(std::sync::atomic::Ordering::SeqCst) {
-        if let Some(temp) = temp_wav {
-            let _ = std::fs::remove_file(&temp);
-        }
-        return Err("Cancelled by user".to_string());
+    if crate::TRANSCRIPTION_CANCELLED.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(inference::CANCELLED.to_string());
     }
 
-    // Simple time-based progress during transcription (progress callback was causing crashes)
-    let app_clone = app.clone();
-    let total_ms = total_duration_ms;
-    let audio_duration_secs = total_duration_ms as f64 / 1000.0;
-    let estimated_process_time = (audio_duration_secs * 0.5).max(5.0);
-    
-    let progress_running = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
-    let progress_running_clone = progress_running.clone();
-    
-    std::thread::spawn(move || {
-        let start = std::time::Instant::now();
-        loop {
-            if !progress_running_clone.load(std::sync::atomic::Ordering::SeqCst) {
-                break;
-            }
-            if crate::SRT_CANCELLED.load(std::sync::atomic::Ordering::SeqCst) {
-                break;
-            }
-            
-            let elapsed = start.elapsed().as_secs_f64();
-            let progress = (elapsed / estimated_process_time).min(1.0);
-            let percentage = 20 + (progress * 60.0) as u32;
-            let estimated_processed = (progress * total_ms as f64) as u64;
-            
-            let _ = app_clone.emit("srt-progress", SrtProgress {
-                percentage,
-                processed_ms: estimated_processed,
-                total_ms,
-                status: "transcribing".to_string(),
-            });
-            
-            std::thread::sleep(std::time::Duration::from_millis(200)); // Update every 200ms
-        }
-    });
-
     // Handle duplicate files logic
     let mut final_output_path = PathBuf::from(&output_path);
     if duplicate_mode == "rename" && final_output_path.exists() {
@@ -366,92 +242,61 @@ pub async fn generate_srt<R: Runtime>(
     
     // Counter for SRT segments
     let mut segment_counter = 1;
-    
-    // Set up segment callback for REAL-TIME writing
-    // This allows data to be saved even if the app crashes mid-process
-    whisper_params.set_segment_callback_safe(move |data: whisper_rs::SegmentCallbackData| {
-        let seg_text = data.text;
-        
-        // Filter hallucinations - same logic as before
-        let lower = seg_text.to_lowercase();
-        let trimmed = seg_text.trim();
-        
-        // Skip common hallucination patterns
-        if lower.contains("subscribe") || lower.contains("amara.org") || 
-           lower.contains("subtitles by") || lower.contains("transcribed by") ||
-           lower.contains("(speaking") {
-            println!("[SRT] Filtered hallucination: {}", trimmed);
-            return;
-        }
-        
-        // Skip pure annotation segments like "[Music]", "[Applause]", etc.
-        if trimmed.starts_with('[') && trimmed.ends_with(']') && !trimmed.contains(' ') {
-            println!("[SRT] Filtered annotation: {}", trimmed);
-            return;
-        }
-        
-        // Remove inline annotations but keep the text
-        let cleaned_text = if trimmed.contains('[') && trimmed.contains(']') {
-            let mut result = String::new();
-            let mut in_bracket = false;
-            for c in trimmed.chars() {
-                if c == '[' { in_bracket = true; continue; }
-                if c == ']' { in_bracket = false; continue; }
-                if !in_bracket { result.push(c); }
-            }
-            // If cleaning resulted in empty string (e.g. "[Music]" becomes ""), skip
-            if result.trim().is_empty() { return; }
-            result
-        } else {
-            trimmed.to_string()
-        };
-        
-        if cleaned_text.trim().is_empty() { return; }
 
-        // Determine timestamp unit based on whether DTW is enabled or not
-        // Whisper default is centiseconds (10ms units)
-        // With DTW enabled, timestamps are more precise but seemingly still scaled?
-        // Let's assume consistent conversion: unit * 10 = milliseconds
-        let start_ms = data.start_timestamp * 10;
-        let end_ms = data.end_timestamp * 10;
-        
-        // Format timestamp: HH:MM:SS,mmm
-        fn format_timestamp(ms: i64) -> String {
-            let hours = ms / 3600000;
-            let minutes = (ms % 3600000) / 60000;
-            let seconds = (ms % 60000) / 1000;
-            let millis = ms % 1000;
-            format!("{:02}:{:02}:{:02},{:03}", hours, minutes, seconds, millis)
-        }
-        
-        let start_fmt = format_timestamp(start_ms);
-        let end_fmt = format_timestamp(end_ms);
-        
-        // Write segment to file immediately
-        let srt_entry = format!("{}\n{} --> {}\n{}\n\n", 
-            segment_counter, start_fmt, end_fmt, cleaned_text.trim());
-        
-        println!("[SRT] Writing segment {}: {} -> {}", segment_counter, start_fmt, end_fmt);
-            
-        if let Err(e) = file.write_all(srt_entry.as_bytes()) {
-            eprintln!("[SRT] Failed to write segment: {}", e);
-        }
-        // Force OS to flush to disk
-        if let Err(e) = file.sync_data() {
-            eprintln!("[SRT] Failed to sync file: {}", e);
-        }
-        
-        segment_counter += 1;
-    });
+    let rules = postprocess::rule_set(&app);
+    let filter = postprocess::filter_rules(&app);
+    let options = ChunkOptions {
+        language: &language.unwrap_or_default().or_track_language(opened.track.language_code),
+        preprocess: &preprocess.unwrap_or_default(),
+        prompt: prompt.as_deref(),
+        filter: &filter,
+        threads: n_threads as usize,
+        words: true,
+        start_ms: range.start_ms,
+    };
 
-    // Process FULL audio in one pass
-    // Segments are now written in real-time via the callback above
-    let result = state.full(whisper_params, &samples);
-    
-    // Stop progress thread
-    progress_running.store(false, std::sync::atomic::Ordering::SeqCst);
-    
-    result.map_err(|e| format!("Failed to run model: {}", e))?;
+    // Each chunk's entries are written as soon as it is done,
+    // so the file survives a crash part way through a long file
+    let chunked = chunked::transcribe_stream(
+        ctx,
+        opened.audio.as_mut(),
+        &options,
+        &crate::TRANSCRIPTION_CANCELLED,
+        || srt_params(translate, &decoding, n_threads),
+        |processed_ms| {
+            let processed_ms = processed_ms.min(total_duration_ms);
+            let _ = app.emit("srt-progress", SrtProgress {
+                percentage: 20 + (processed_ms * 60 / total_duration_ms.max(1)) as u32,
+                processed_ms,
+                total_ms: total_duration_ms,
+                status: "transcribing".to_string(),
+            });
+        },
+        |segments| {
+            for segment in segments {
+                let entry = srt_entry(segment_counter, segment.start_ms as i64, segment.end_ms as i64, &rules.apply(&segment.text));
+                file.write_all(entry.as_bytes()).map_err(|e| format!("Failed to write subtitles: {}", e))?;
+                segment_counter += 1;
+            }
+            // Force OS to flush to disk
+            if let Err(e) = file.sync_data() {
+                eprintln!("[SRT] Failed to sync file: {}", e);
+            }
+            println!("[SRT] Wrote {} segments", segment_counter - 1);
+            Ok(())
+        },
+    )?;
+
+    let mut transcript = Transcript {
+        text: transcript::join_text(&chunked.segments),
+        segments: chunked.segments,
+        filtered: chunked.filtered,
+        language: chunked.language,
+        model,
+        backend: transcript::backend_name(use_gpu),
+        processing_ms: 0,
+    };
+    transcript.apply_rules(&rules);
 
     // Emit progress after transcription
     let _ = app.emit("srt-progress", SrtProgress {
@@ -460,19 +305,24 @@ pub async fn generate_srt<R: Runtime>(
         total_ms: total_duration_ms,
         status: "complete".to_string(),
     });
-    
-    // We kept the file open in the closure, so it simply closes when dropped.
-    // Logic for duplicate handling and path generation was done before this block
-    // and `final_output_path` was used.
 
-    /* REMOVED: Old post-processing loop 
-       Segments are already written to disk!
-    */
-    // Clean up temp file
-    if let Some(temp) = temp_wav {
-        let _ = std::fs::remove_file(&temp);
-    }
+    transcript.processing_ms = started.elapsed().as_millis() as u64;
 
-    Ok(final_output_path.to_string_lossy().to_string())
-}
+    // A recorded take is kept unless the user opted out; a file the user picked always stays
+    drop(opened);
+    let archive = Path::new(&audio_path);
+    let archive_path = if !super::audio::is_archive(&app, archive) {
+        None
+    } else if keep_archive.unwrap_or(true) {
+        Some(audio_path.clone())
+    } else {
+        super::audio::remove_archive(&app, archive);
+        None
+    };
 
+    Ok(SrtResult {
+        path: final_output_path.to_string_lossy().to_string(),
+        transcript,
+        archive_path,
+    })
+}
@@ -95,7 +95,7 @@ pub async fn paste_text() -> Result<(), String> {
             err_msg.push_str(" Install ydotool & run ydotoold.");
         }
 
-        return Err(err_msg);
+        Err(err_msg)
     } else {
         // Pure X11: xdotool works reliably
         let res = std::process::Command::new("xdotool")
@@ -103,9 +103,9 @@ pub async fn paste_text() -> Result<(), String> {
             .output();
             
         match res {
-            Ok(output) if output.status.success() => return Ok(()),
-            Ok(output) => return Err(format!("X11 DETECTED. xdotool failed with code: {:?}", output.status.code())),
-            Err(e) => return Err(format!("X11 DETECTED (xdg_session: {}). xdotool failed: {}", xdg_session, e)),
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => Err(format!("X11 DETECTED. xdotool failed with code: {:?}", output.status.code())),
+            Err(e) => Err(format!("X11 DETECTED (xdg_session: {}). xdotool failed: {}", xdg_session, e)),
         }
     }
 }
@@ -126,6 +126,7 @@ use sysinfo::System;
 pub struct SystemStats {
     total_memory: u64,
     free_memory: u64,
+    available_memory: u64,
     cpu_cores: usize,
     has_nvidia: bool,
     has_amd: bool,
@@ -139,18 +140,11 @@ pub fn get_system_stats() -> SystemStats {
 
     let total_memory = sys.total_memory();
     let free_memory = sys.free_memory();
+    let available_memory = sys.available_memory();
     let cpu_cores = sys.cpus().len();
     
     // Features check
-    let backend = if cfg!(feature = "cuda") {
-        "CUDA (NVIDIA)".to_string()
-    } else if cfg!(feature = "vulkan") {
-        "Vulkan".to_string()
-    } else if cfg!(feature = "rocm") {
-        "ROCm (AMD)".to_string()
-    } else {
-        "CPU".to_string()
-    };
+    let backend = compiled_backend().to_string();
 
     // Check for GPU presence via CLI commands (linux only for now)
     let has_nvidia = std::path::Path::new("/usr/bin/nvidia-smi").exists() || 
@@ -162,6 +156,7 @@ pub fn get_system_stats() -> SystemStats {
     SystemStats {
         total_memory,
         free_memory,
+        available_memory,
         cpu_cores,
         has_nvidia,
         has_amd,
@@ -169,6 +164,28 @@ pub fn get_system_stats() -> SystemStats {
     }
 }
 
+/// GPU backend this build was compiled with, "CPU" if none
+pub(crate) fn compiled_backend() -> &'static str {
+    if cfg!(feature = "cuda") {
+        "CUDA (NVIDIA)"
+    } else if cfg!(feature = "vulkan") {
+        "Vulkan"
+    } else if cfg!(feature = "rocm") {
+        "ROCm (AMD)"
+    } else {
+        "CPU"
+    }
+}
+
+/// Memory that can be allocated without swapping. Unlike `free_memory` this
+/// counts reclaimable page cache, so it is what sizes in-memory buffers.
+/// Free memory in bytes, `None` where the platform doesn't report it
+pub(crate) fn available_memory() -> Option<u64> {
+    let mut sys = System::new();
+    sys.refresh_memory();
+    Some(sys.available_memory()).filter(|&bytes| bytes > 0)
+}
+
 #[tauri::command]
 pub fn send_notification(title: String, body: String) -> Result<(), String> {
     #[cfg(target_os = "linux")]
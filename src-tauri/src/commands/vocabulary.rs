@@ -0,0 +1,221 @@
+// User vocabulary (names, products, jargon) fed to Whisper as the initial prompt.
+// Lists live in vocabulary.json in the app data dir so every command sees the same ones;
+// a list can belong to one profile, so only that profile's transcriptions use it.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime};
+use whisper_rs::WhisperContext;
+use crate::storage;
+use super::local::{resolve_model_path, with_context};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VocabularyList {
+    /// Assigned on first save
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub terms: Vec<String>,
+    /// Disabled lists are kept but not used in the prompt
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Profile the list belongs to, unset for lists used in every profile
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Prompt built from the enabled lists and what it costs
+#[derive(Clone, Serialize)]
+pub struct VocabularyPrompt {
+    /// What is sent, without `dropped`
+    pub prompt: String,
+    /// Tokens of all enabled terms
+    pub tokens: usize,
+    /// Whisper keeps only the last `max_tokens` of the prompt
+    pub max_tokens: usize,
+    /// Lowest-priority terms left out to stay within `max_tokens`
+    pub dropped: Vec<String>,
+}
+
+/// Enabled terms in priority order: list order, then term order
+pub(crate) struct Vocabulary {
+    terms: Vec<String>,
+}
+
+const STORE_FILE: &str = "vocabulary.json";
+
+fn load<R: Runtime>(app: &AppHandle<R>) -> Result<Vec<VocabularyList>, String> {
+    storage::load_json(app, STORE_FILE)
+}
+
+fn save<R: Runtime>(app: &AppHandle<R>, lists: &[VocabularyList]) -> Result<(), String> {
+    storage::save_json(app, STORE_FILE, lists)
+}
+
+#[tauri::command]
+pub fn list_vocabularies<R: Runtime>(app: AppHandle<R>) -> Result<Vec<VocabularyList>, String> {
+    load(&app)
+}
+
+/// Create or update a list. Returns all lists.
+#[tauri::command]
+pub fn save_vocabulary<R: Runtime>(app: AppHandle<R>, mut list: VocabularyList) -> Result<Vec<VocabularyList>, String> {
+    let mut lists = load(&app)?;
+    list.terms = list.terms.iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+    list.profile = list.profile.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+
+    match lists.iter_mut().find(|l| !list.id.is_empty() && l.id == list.id) {
+        Some(existing) => *existing = list,
+        None => {
+            list.id = storage::new_id("vocab");
+            lists.push(list);
+        }
+    }
+    save(&app, &lists)?;
+    Ok(lists)
+}
+
+/// Returns the remaining lists.
+#[tauri::command]
+pub fn delete_vocabulary<R: Runtime>(app: AppHandle<R>, id: String) -> Result<Vec<VocabularyList>, String> {
+    let mut lists = load(&app)?;
+    lists.retain(|l| l.id != id);
+    save(&app, &lists)?;
+    Ok(lists)
+}
+
+/// Terms of the enabled lists of `profile` and of every profile, without repeats
+fn active_terms(lists: &[VocabularyList], profile: Option<&str>) -> Vec<String> {
+    let profile = profile.map(str::trim).filter(|p| !p.is_empty());
+    let mut terms: Vec<String> = Vec::new();
+    let active = lists.iter().filter(|l| l.enabled && (l.profile.is_none() || l.profile.as_deref() == profile));
+    for term in active.flat_map(|l| &l.terms) {
+        if !terms.iter().any(|t| t.eq_ignore_ascii_case(term)) {
+            terms.push(term.clone());
+        }
+    }
+    terms
+}
+
+/// Vocabulary of `profile`, `None` when it has no terms.
+/// A failing store never blocks a transcription, it just runs without a prompt.
+pub(crate) fn prompt<R: Runtime>(app: &AppHandle<R>, profile: Option<&str>) -> Option<Vocabulary> {
+    let lists = match load(app) {
+        Ok(lists) => lists,
+        Err(e) => {
+            eprintln!("[Vocabulary] {}", e);
+            return None;
+        }
+    };
+    let terms = active_terms(&lists, profile);
+    if terms.is_empty() {
+        return None;
+    }
+    Some(Vocabulary { terms })
+}
+
+fn join(terms: &[String]) -> String {
+    format!("{}.", terms.join(", "))
+}
+
+fn count_tokens(ctx: &WhisperContext, text: &str) -> Result<usize, String> {
+    // A token is at least one byte, so this bound never truncates
+    ctx.tokenize(text, text.len() + 1)
+        .map(|tokens| tokens.len())
+        .map_err(|e| format!("Failed to tokenize vocabulary: {}", e))
+}
+
+impl Vocabulary {
+    /// Keep the longest run of leading terms that fits in `n_text_ctx / 2` tokens
+    /// minus `reserved`, which the caller appends after the vocabulary.
+    fn measure(&self, ctx: &WhisperContext, reserved: usize) -> Result<VocabularyPrompt, String> {
+        let max_tokens = (ctx.n_text_ctx() / 2) as usize;
+        let budget = max_tokens.saturating_sub(reserved);
+        let tokens = count_tokens(ctx, &join(&self.terms))?;
+
+        let mut kept = self.terms.len();
+        if tokens > budget {
+            // Token counts grow with the number of terms, so search for the cut
+            let (mut fits, mut over) = (0, self.terms.len());
+            while over - fits > 1 {
+                let mid = (fits + over) / 2;
+                if count_tokens(ctx, &join(&self.terms[..mid]))? <= budget {
+                    fits = mid;
+                } else {
+                    over = mid;
+                }
+            }
+            kept = fits;
+        }
+        Ok(VocabularyPrompt {
+            prompt: if kept == 0 { String::new() } else { join(&self.terms[..kept]) },
+            tokens,
+            max_tokens,
+            dropped: self.terms[kept..].to_vec(),
+        })
+    }
+
+    /// The prompt to send, truncated to the model's budget. The UI is told which terms were left out.
+    /// `None` when not even the first term fits.
+    pub(crate) fn fit<R: Runtime>(&self, app: &AppHandle<R>, ctx: &WhisperContext, reserved: usize) -> Option<String> {
+        match self.measure(ctx, reserved) {
+            Ok(measured) => {
+                if !measured.dropped.is_empty() {
+                    println!("[Vocabulary] Prompt has {} tokens, dropped {} terms to fit {}", measured.tokens, measured.dropped.len(), measured.max_tokens);
+                    let _ = app.emit("vocabulary-truncated", measured.clone());
+                }
+                Some(measured.prompt).filter(|p| !p.is_empty())
+            }
+            Err(e) => {
+                // Whisper still keeps the end of an untruncated prompt
+                eprintln!("[Vocabulary] {}", e);
+                Some(join(&self.terms))
+            }
+        }
+    }
+}
+
+/// Current prompt of `profile` and its token count for the given model, for the settings screen
+#[tauri::command]
+pub async fn get_vocabulary_prompt<R: Runtime>(app: AppHandle<R>, model: String, use_gpu: bool, profile: Option<String>) -> Result<Option<VocabularyPrompt>, String> {
+    let vocabulary = match prompt(&app, profile.as_deref()) {
+        Some(vocabulary) => vocabulary,
+        None => return Ok(None),
+    };
+    let model_path = resolve_model_path(&app, &model)?;
+    with_context(&app, &model_path, use_gpu, || {}, |ctx| vocabulary.measure(ctx, 0)).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(terms: &[&str], profile: Option<&str>) -> VocabularyList {
+        VocabularyList {
+            id: String::new(),
+            name: String::new(),
+            terms: terms.iter().map(|t| t.to_string()).collect(),
+            enabled: true,
+            profile: profile.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn profiles_add_their_lists_to_the_shared_ones() {
+        let lists = [list(&["Yappie"], None), list(&["Kubernetes"], Some("work")), list(&["Priya"], Some("home"))];
+        assert_eq!(active_terms(&lists, None), ["Yappie"]);
+        assert_eq!(active_terms(&lists, Some("work")), ["Yappie", "Kubernetes"]);
+        assert_eq!(active_terms(&lists, Some(" ")), ["Yappie"]);
+    }
+
+    #[test]
+    fn disabled_lists_and_repeats_are_left_out() {
+        let mut off = list(&["Helm"], None);
+        off.enabled = false;
+        let lists = [list(&["Yappie", "Kubernetes"], None), off, list(&["kubernetes", "Priya"], Some("work"))];
+        assert_eq!(active_terms(&lists, Some("work")), ["Yappie", "Kubernetes", "Priya"]);
+    }
+}
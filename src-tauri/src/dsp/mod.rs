@@ -0,0 +1,157 @@
+// Shared audio signal processing for capture and file transcription
+
+pub mod preprocess;
+pub mod resample;
+pub mod vad;
+
+/// Whisper only accepts 16kHz mono input
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+pub fn samples_to_ms(samples: usize) -> u64 {
+    samples as u64 * 1000 / WHISPER_SAMPLE_RATE as u64
+}
+
+pub fn ms_to_samples(ms: u64) -> usize {
+    (ms * WHISPER_SAMPLE_RATE as u64 / 1000) as usize
+}
+
+/// Average interleaved frames of any channel count down to mono.
+pub fn downmix(interleaved: &[f32], channels: usize, output: &mut Vec<f32>) {
+    if channels <= 1 {
+        output.extend_from_slice(interleaved);
+        return;
+    }
+    let scale = 1.0 / channels as f32;
+    output.extend(
+        interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() * scale),
+    );
+}
+
+/// Peak level of `points` equal slices of the audio, for drawing a waveform
+pub fn envelope(samples: &[f32], points: usize) -> Vec<f32> {
+    if samples.is_empty() || points == 0 {
+        return Vec::new();
+    }
+    let chunk = samples.len().div_ceil(points);
+    samples
+        .chunks(chunk)
+        .map(|c| c.iter().fold(0.0f32, |m, s| m.max(s.abs())))
+        .collect()
+}
+
+/// 16kHz mono audio read from a file a block at a time, so long files never have to fit in memory
+pub trait SampleStream {
+    /// Append about `max` samples to `output` and return how many were added, 0 at the end.
+    fn read(&mut self, max: usize, output: &mut Vec<f32>) -> Result<usize, String>;
+    /// Length of the whole file, 0 when the container doesn't say
+    fn duration_ms(&self) -> u64;
+}
+
+/// A WAV file of any bit depth, channel count and rate
+pub struct WavStream {
+    reader: hound::WavReader<std::io::BufReader<std::fs::File>>,
+    spec: hound::WavSpec,
+    resampler: resample::Resampler,
+    interleaved: Vec<f32>,
+    mono: Vec<f32>,
+    finished: bool,
+}
+
+impl WavStream {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let reader = hound::WavReader::open(path)
+            .map_err(|e| format!("Failed to open wav file: {}", e))?;
+        let spec = reader.spec();
+        Ok(Self {
+            reader,
+            spec,
+            resampler: resample::Resampler::new(spec.sample_rate, WHISPER_SAMPLE_RATE)?,
+            interleaved: Vec::new(),
+            mono: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Start reading at `ms` into the file; call it before the first read.
+    pub fn seek(&mut self, ms: u64) -> Result<(), String> {
+        let frame = (ms * self.spec.sample_rate as u64 / 1000).min(self.reader.duration() as u64);
+        self.reader.seek(frame as u32).map_err(|e| format!("Failed to seek in wav file: {}", e))
+    }
+}
+
+impl SampleStream for WavStream {
+    fn duration_ms(&self) -> u64 {
+        self.reader.duration() as u64 * 1000 / self.spec.sample_rate.max(1) as u64
+    }
+
+    fn read(&mut self, max: usize, output: &mut Vec<f32>) -> Result<usize, String> {
+        let channels = self.spec.channels.max(1) as usize;
+        // Input frames that resample to `max` output samples
+        let frames = (max as u64 * self.spec.sample_rate as u64).div_ceil(WHISPER_SAMPLE_RATE as u64).max(1) as usize;
+        let wanted = frames * channels;
+        let before = output.len();
+
+        // The resampler holds back its filter length, keep going until something comes out
+        while output.len() == before && !self.finished {
+            self.interleaved.clear();
+            match self.spec.sample_format {
+                hound::SampleFormat::Float => self.interleaved.extend(
+                    self.reader.samples::<f32>().take(wanted).map(|s| s.unwrap_or(0.0)),
+                ),
+                hound::SampleFormat::Int => {
+                    let scale = 1.0 / (1i64 << (self.spec.bits_per_sample - 1)) as f32;
+                    self.interleaved.extend(
+                        self.reader.samples::<i32>().take(wanted).map(|s| s.unwrap_or(0) as f32 * scale),
+                    );
+                }
+            }
+
+            self.mono.clear();
+            downmix(&self.interleaved, channels, &mut self.mono);
+            self.resampler.process(&self.mono, output);
+            if self.interleaved.len() < wanted {
+                self.resampler.flush(output);
+                self.finished = true;
+            }
+        }
+        Ok(output.len() - before)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / WHISPER_SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn envelope_follows_the_peaks() {
+        let mut samples = sine(0.5, 16000);
+        samples.extend(vec![0.0; 16000]);
+        let points = envelope(&samples, 100);
+        assert_eq!(points.len(), 100);
+        assert!(points[..50].iter().all(|&p| (p - 0.5).abs() < 0.01));
+        assert!(points[50..].iter().all(|&p| p == 0.0));
+    }
+
+    #[test]
+    fn envelope_of_silence_is_flat() {
+        assert_eq!(envelope(&[0.0; 800], 200), vec![0.0; 200]);
+        assert!(envelope(&[], 200).is_empty());
+        assert!(envelope(&[0.1; 10], 0).is_empty());
+    }
+
+    #[test]
+    fn envelope_never_has_more_points_than_asked() {
+        // Shorter audio gets a point per sample, uneven lengths round the slices up
+        assert_eq!(envelope(&[0.1, -0.3, 0.2], 200), vec![0.1, 0.3, 0.2]);
+        let points = envelope(&sine(0.5, 1001), 200);
+        assert!(!points.is_empty() && points.len() <= 200);
+    }
+}
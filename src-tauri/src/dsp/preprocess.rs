@@ -0,0 +1,323 @@
+// Optional cleanup of 16kHz mono audio before it reaches Whisper:
+// high-pass, spectral-gate denoise and loudness normalization.
+// Fan hum and office noise are what make Whisper invent text in pauses.
+
+use realfft::RealFftPlanner;
+use serde::Deserialize;
+use super::WHISPER_SAMPLE_RATE;
+
+/// Which stages to run. All off by default so audio reaches Whisper untouched.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PreprocessOptions {
+    pub high_pass: bool,
+    pub denoise: bool,
+    pub normalize: bool,
+}
+
+/// Rumble, handling noise and mains hum below speech
+const HIGH_PASS_HZ: f32 = 80.0;
+
+/// 32ms STFT frames with 50% overlap
+const FRAME: usize = 512;
+const HOP: usize = FRAME / 2;
+/// Share of the quietest frames that make up the noise profile
+const NOISE_PERCENTILE: f32 = 0.1;
+/// Subtract a bit more than the measured noise so the gate actually closes
+const OVER_SUBTRACTION: f32 = 2.0;
+/// Neighbouring bins on each side averaged before gating
+const SMOOTH_BINS: usize = 2;
+/// Never attenuate a bin by more than -20dB; a dead-silent gap sounds worse than a quiet one
+const GAIN_FLOOR: f32 = 0.1;
+/// How much of the previous frame's gain survives when a bin closes (less musical noise)
+const GAIN_RELEASE: f32 = 0.5;
+
+/// 100ms blocks for loudness measurement
+const BLOCK: usize = WHISPER_SAMPLE_RATE as usize / 10;
+/// Blocks below -60dBFS are silence and don't count towards loudness
+const ABSOLUTE_GATE: f32 = 0.001;
+/// Blocks 10dB below the average of the rest are pauses, not speech
+const RELATIVE_GATE: f32 = 0.1;
+/// Speech level to normalize to, -20dBFS RMS
+const TARGET_RMS: f32 = 0.1;
+/// Don't boost by more than +30dB
+const MAX_GAIN: f32 = 31.6;
+const PEAK_CEILING: f32 = 0.97;
+
+/// Run the enabled stages in place, in chain order.
+pub fn process(samples: &mut [f32], opts: &PreprocessOptions) {
+    if opts.high_pass {
+        high_pass(samples, HIGH_PASS_HZ);
+    }
+    if opts.denoise {
+        denoise(samples);
+    }
+    if opts.normalize {
+        normalize(samples);
+    }
+}
+
+/// Second-order Butterworth high-pass (RBJ cookbook biquad).
+pub fn high_pass(samples: &mut [f32], cutoff_hz: f32) {
+    let w0 = 2.0 * std::f32::consts::PI * cutoff_hz / WHISPER_SAMPLE_RATE as f32;
+    let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+    let cos = w0.cos();
+    let a0 = 1.0 + alpha;
+    let b0 = (1.0 + cos) / 2.0 / a0;
+    let b1 = -(1.0 + cos) / a0;
+    let b2 = b0;
+    let a1 = -2.0 * cos / a0;
+    let a2 = (1.0 - alpha) / a0;
+
+    // Transposed direct form II
+    let (mut z1, mut z2) = (0.0f32, 0.0f32);
+    for s in samples.iter_mut() {
+        let x = *s;
+        let y = b0 * x + z1;
+        z1 = b1 * x - a1 * y + z2;
+        z2 = b2 * x - a2 * y;
+        *s = y;
+    }
+}
+
+/// Spectral gate. The noise profile is the average spectrum of the quietest frames,
+/// so the input needs some pauses between words for it to be accurate.
+pub fn denoise(samples: &mut [f32]) {
+    if samples.len() < FRAME * 4 {
+        return;
+    }
+
+    // sqrt-Hann on both analysis and synthesis sums to one at 50% overlap
+    let window: Vec<f32> = (0..FRAME)
+        .map(|n| (std::f32::consts::PI * n as f32 / FRAME as f32).sin())
+        .collect();
+
+    // Pad so every real sample is covered by two frames
+    let mut padded = vec![0.0f32; HOP + samples.len() + FRAME];
+    padded[HOP..HOP + samples.len()].copy_from_slice(samples);
+    let n_frames = (padded.len() - FRAME) / HOP + 1;
+
+    let mut planner = RealFftPlanner::<f32>::new();
+    let forward = planner.plan_fft_forward(FRAME);
+    let inverse = planner.plan_fft_inverse(FRAME);
+    let mut frame = forward.make_input_vec();
+    let mut spectrum = forward.make_output_vec();
+    let bins = spectrum.len();
+
+    // Rank frames by energy without running the FFT over everything twice.
+    // Frames reaching into the padding would look quiet without being noise.
+    let interior = 1..(samples.len() - FRAME) / HOP + 1;
+    let mut energies: Vec<(f32, usize)> = interior
+        .map(|i| {
+            let start = i * HOP;
+            let energy = padded[start..start + FRAME]
+                .iter()
+                .zip(&window)
+                .map(|(s, w)| (s * w) * (s * w))
+                .sum::<f32>();
+            (energy, i)
+        })
+        .collect();
+    energies.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let quiet = ((energies.len() as f32 * NOISE_PERCENTILE) as usize).max(1);
+
+    let mut noise = vec![0.0f32; bins];
+    for &(_, i) in &energies[..quiet] {
+        let start = i * HOP;
+        for ((f, s), w) in frame.iter_mut().zip(&padded[start..start + FRAME]).zip(&window) {
+            *f = s * w;
+        }
+        if forward.process(&mut frame, &mut spectrum).is_err() {
+            return;
+        }
+        for (n, c) in noise.iter_mut().zip(&spectrum) {
+            *n += c.norm_sqr() / quiet as f32;
+        }
+    }
+
+    let mut output = vec![0.0f32; padded.len()];
+    let mut gains = vec![1.0f32; bins];
+    let mut power = vec![0.0f32; bins];
+    let scale = 1.0 / FRAME as f32;
+    for i in 0..n_frames {
+        let start = i * HOP;
+        for ((f, s), w) in frame.iter_mut().zip(&padded[start..start + FRAME]).zip(&window) {
+            *f = s * w;
+        }
+        if forward.process(&mut frame, &mut spectrum).is_err() {
+            return;
+        }
+
+        // Decide on power averaged over neighbouring bins; single bins of noise
+        // fluctuate too much and leave "musical" chirps behind
+        for (b, p) in power.iter_mut().enumerate() {
+            let lo = b.saturating_sub(SMOOTH_BINS);
+            let hi = (b + SMOOTH_BINS + 1).min(bins);
+            *p = spectrum[lo..hi].iter().map(|c| c.norm_sqr()).sum::<f32>() / (hi - lo) as f32;
+        }
+        for b in 0..bins {
+            let target = if power[b] > 0.0 {
+                (1.0 - OVER_SUBTRACTION * noise[b] / power[b]).max(GAIN_FLOOR * GAIN_FLOOR).sqrt()
+            } else {
+                GAIN_FLOOR
+            };
+            // Open instantly, close gradually
+            let g = &mut gains[b];
+            *g = if target >= *g { target } else { target.max(*g * GAIN_RELEASE) };
+            spectrum[b] *= *g;
+        }
+        // The inverse transform rejects imaginary parts on DC and Nyquist
+        spectrum[0].im = 0.0;
+        spectrum[bins - 1].im = 0.0;
+
+        if inverse.process(&mut spectrum, &mut frame).is_err() {
+            return;
+        }
+        for ((o, f), w) in output[start..start + FRAME].iter_mut().zip(&frame).zip(&window) {
+            *o += f * w * scale;
+        }
+    }
+
+    samples.copy_from_slice(&output[HOP..HOP + samples.len()]);
+}
+
+/// Bring speech to a common level, measured over gated 100ms blocks so pauses
+/// don't drag the estimate down. Peaks are kept below clipping.
+pub fn normalize(samples: &mut [f32]) {
+    let powers: Vec<f32> = samples
+        .chunks(BLOCK)
+        .map(|b| b.iter().map(|s| s * s).sum::<f32>() / b.len() as f32)
+        .filter(|p| *p > ABSOLUTE_GATE * ABSOLUTE_GATE)
+        .collect();
+    if powers.is_empty() {
+        return;
+    }
+
+    let mean = powers.iter().sum::<f32>() / powers.len() as f32;
+    let gated: Vec<f32> = powers.into_iter().filter(|p| *p >= mean * RELATIVE_GATE).collect();
+    let loudness = (gated.iter().sum::<f32>() / gated.len() as f32).sqrt();
+
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    let gain = (TARGET_RMS / loudness).min(MAX_GAIN).min(PEAK_CEILING / peak);
+    for s in samples.iter_mut() {
+        *s *= gain;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = WHISPER_SAMPLE_RATE as f32;
+
+    fn sine(freq: f32, amplitude: f32, secs: f32) -> Vec<f32> {
+        (0..(RATE * secs) as usize)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / RATE).sin())
+            .collect()
+    }
+
+    /// Deterministic white noise in [-amplitude, amplitude]
+    fn noise(amplitude: f32, len: usize) -> Vec<f32> {
+        let mut state = 0x2545_f491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn disabled_chain_leaves_audio_untouched() {
+        let original = sine(440.0, 0.3, 0.5);
+        let mut samples = original.clone();
+        process(&mut samples, &PreprocessOptions::default());
+        assert_eq!(samples, original);
+    }
+
+    #[test]
+    fn high_pass_removes_rumble_and_keeps_speech_band() {
+        let mut rumble = sine(20.0, 0.5, 2.0);
+        high_pass(&mut rumble, HIGH_PASS_HZ);
+        // Skip the filter's settling time
+        assert!(rms(&rumble[8000..]) < 0.5 / 2f32.sqrt() * 0.1);
+
+        let mut voice = sine(1000.0, 0.5, 2.0);
+        high_pass(&mut voice, HIGH_PASS_HZ);
+        let ratio = rms(&voice[8000..]) / (0.5 / 2f32.sqrt());
+        assert!((ratio - 1.0).abs() < 0.02, "passband gain {}", ratio);
+    }
+
+    #[test]
+    fn denoise_gates_noise_and_keeps_tone() {
+        // One second of noise alone, then a tone over the same noise
+        let len = (RATE * 3.0) as usize;
+        let hiss = noise(0.02, len);
+        let tone = sine(500.0, 0.3, 3.0);
+        let mut samples: Vec<f32> = hiss
+            .iter()
+            .zip(&tone)
+            .enumerate()
+            .map(|(i, (n, t))| if i < RATE as usize { *n } else { n + t })
+            .collect();
+
+        denoise(&mut samples);
+
+        let noise_before = rms(&hiss[1000..15000]);
+        let noise_after = rms(&samples[1000..15000]);
+        assert!(noise_after < noise_before * 0.3, "noise {} -> {}", noise_before, noise_after);
+
+        let tone_after = rms(&samples[20000..44000]);
+        let tone_before = rms(&tone[20000..44000]);
+        assert!((tone_after / tone_before - 1.0).abs() < 0.1, "tone {} -> {}", tone_before, tone_after);
+    }
+
+    #[test]
+    fn denoise_is_transparent_without_noise() {
+        // Tone bursts with digital silence between them: the profile is all zeros
+        let mut original = Vec::new();
+        for _ in 0..3 {
+            original.extend(sine(300.0, 0.2, 0.5));
+            original.extend(vec![0.0f32; RATE as usize / 2]);
+        }
+        let mut samples = original.clone();
+        denoise(&mut samples);
+        let error: Vec<f32> = samples.iter().zip(&original).map(|(a, b)| a - b).collect();
+        assert!(rms(&error) < rms(&original) * 0.01, "error {}", rms(&error));
+    }
+
+    #[test]
+    fn normalize_brings_quiet_speech_to_target() {
+        let mut samples = sine(440.0, 0.01, 2.0);
+        normalize(&mut samples);
+        assert!((rms(&samples) - TARGET_RMS).abs() < 0.005, "rms {}", rms(&samples));
+    }
+
+    #[test]
+    fn normalize_ignores_pauses_and_respects_peaks() {
+        // Half speech, half near-silence: the level is measured on the speech only
+        let mut samples = sine(440.0, 0.05, 1.0);
+        samples.extend(noise(0.0005, RATE as usize));
+        normalize(&mut samples);
+        let speech = rms(&samples[..RATE as usize]);
+        assert!((speech - TARGET_RMS).abs() < 0.005, "speech rms {}", speech);
+
+        let mut loud = sine(440.0, 0.9, 1.0);
+        loud[100] = 1.0;
+        normalize(&mut loud);
+        assert!(loud.iter().all(|s| s.abs() <= PEAK_CEILING + 1e-6));
+    }
+
+    #[test]
+    fn normalize_leaves_silence_alone() {
+        let mut samples = vec![0.0f32; 16000];
+        normalize(&mut samples);
+        assert!(samples.iter().all(|s| *s == 0.0));
+    }
+}
@@ -0,0 +1,228 @@
+// Band-limited sample rate conversion
+// Windowed-sinc interpolation with a precomputed kernel table. Streaming, so the
+// capture callback can push chunks of any size and get 16kHz out as it goes.
+
+use std::f64::consts::PI;
+
+/// Zero crossings of the sinc on each side of the centre tap
+const ZERO_CROSSINGS: usize = 16;
+/// Kernel table resolution (entries per input sample)
+const PHASES: usize = 256;
+/// Fraction of the output Nyquist frequency kept when downsampling
+const ROLLOFF: f64 = 0.94;
+
+pub struct Resampler {
+    /// Input samples advanced per output sample
+    step: f64,
+    /// Kernel half-width in input samples
+    half_width: f64,
+    /// One side of the symmetric kernel, sampled every 1/PHASES input samples
+    table: Vec<f32>,
+    /// Pending input; index 0 is the oldest sample still needed
+    buffer: Vec<f32>,
+    /// Position of the next output sample in `buffer` coordinates
+    pos: f64,
+    total_in: u64,
+    total_out: u64,
+    ratio: f64,
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Result<Self, String> {
+        // A zero rate would make the step infinite and `process` never advance
+        if from_rate == 0 || to_rate == 0 {
+            return Err(format!("Invalid sample rate conversion: {} Hz to {} Hz", from_rate, to_rate));
+        }
+        let ratio = to_rate as f64 / from_rate as f64;
+        // Cut off below whichever Nyquist is lower
+        let cutoff = ratio.min(1.0) * ROLLOFF;
+        let half_width = ZERO_CROSSINGS as f64 / cutoff;
+
+        let table_len = (half_width * PHASES as f64).ceil() as usize + 2;
+        let table = (0..table_len)
+            .map(|k| {
+                let x = k as f64 / PHASES as f64;
+                if x >= half_width {
+                    return 0.0;
+                }
+                let arg = PI * cutoff * x;
+                let sinc = if arg == 0.0 { 1.0 } else { arg.sin() / arg };
+                // Blackman window over [-half_width, half_width]
+                let w = 0.42 + 0.5 * (PI * x / half_width).cos() + 0.08 * (2.0 * PI * x / half_width).cos();
+                (cutoff * sinc * w) as f32
+            })
+            .collect();
+
+        // Pre-pad with silence so the first output is centred on the first input sample
+        let pad = half_width.ceil() as usize;
+        Ok(Self {
+            step: 1.0 / ratio,
+            half_width,
+            table,
+            buffer: vec![0.0; pad],
+            pos: pad as f64,
+            total_in: 0,
+            total_out: 0,
+            ratio,
+        })
+    }
+
+    fn is_passthrough(&self) -> bool {
+        self.ratio == 1.0
+    }
+
+    fn kernel(&self, distance: f64) -> f32 {
+        let x = distance.abs() * PHASES as f64;
+        let idx = x as usize;
+        if idx + 1 >= self.table.len() {
+            return 0.0;
+        }
+        let frac = (x - idx as f64) as f32;
+        self.table[idx] * (1.0 - frac) + self.table[idx + 1] * frac
+    }
+
+    /// Feed mono input and append every output sample that can be produced so far.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.total_in += input.len() as u64;
+        if self.is_passthrough() {
+            output.extend_from_slice(input);
+            self.total_out += input.len() as u64;
+            return;
+        }
+
+        self.buffer.extend_from_slice(input);
+        self.drain(output);
+    }
+
+    fn drain(&mut self, output: &mut Vec<f32>) {
+        while self.pos + self.half_width < self.buffer.len() as f64 {
+            let first = (self.pos - self.half_width).ceil().max(0.0) as usize;
+            let last = ((self.pos + self.half_width).floor() as usize).min(self.buffer.len() - 1);
+            let mut acc = 0.0f32;
+            for i in first..=last {
+                acc += self.buffer[i] * self.kernel(self.pos - i as f64);
+            }
+            output.push(acc);
+            self.total_out += 1;
+            self.pos += self.step;
+        }
+
+        // Drop input that no future output can reach
+        let consumed = (self.pos - self.half_width).floor().max(0.0) as usize;
+        if consumed > 0 {
+            self.buffer.drain(..consumed.min(self.buffer.len()));
+            self.pos -= consumed as f64;
+        }
+    }
+
+    /// Push out the filter tail. Total output length ends up matching `input_len * ratio`.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        if self.is_passthrough() {
+            return;
+        }
+        let expected = (self.total_in as f64 * self.ratio).round() as u64;
+        let produced = self.total_out;
+        let pad = self.half_width.ceil() as usize + self.step.ceil() as usize + 1;
+        self.buffer.extend(std::iter::repeat_n(0.0, pad));
+
+        let mut tail = Vec::new();
+        self.drain(&mut tail);
+        let keep = (expected.saturating_sub(produced) as usize).min(tail.len());
+        output.extend_from_slice(&tail[..keep]);
+        self.total_out = produced + keep as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One-shot conversion of a whole mono buffer
+    fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+        let mut resampler = Resampler::new(from_rate, to_rate).unwrap();
+        let mut out = Vec::new();
+        resampler.process(samples, &mut out);
+        resampler.flush(&mut out);
+        out
+    }
+
+    fn tone(freq: f64, rate: u32, len: usize) -> Vec<f32> {
+        (0..len).map(|i| (0.5 * (2.0 * PI * freq * i as f64 / rate as f64).sin()) as f32).collect()
+    }
+
+    /// RMS away from both ends, where the filter is still settling
+    fn middle_rms(samples: &[f32]) -> f32 {
+        let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        (middle.iter().map(|s| s * s).sum::<f32>() / middle.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn output_length_follows_the_ratio() {
+        for (from, to) in [(48000, 16000), (44100, 16000), (22050, 16000), (8000, 16000)] {
+            let input = tone(440.0, from, from as usize + 37);
+            let expected = (input.len() as f64 * to as f64 / from as f64).round() as usize;
+            let mut resampler = Resampler::new(from, to).unwrap();
+            let mut out = Vec::new();
+            resampler.process(&input, &mut out);
+            // The filter holds back its last half width until flushed
+            assert!(out.len() < expected, "{} -> {}", from, to);
+            resampler.flush(&mut out);
+            assert_eq!(out.len(), expected, "{} -> {}", from, to);
+        }
+    }
+
+    #[test]
+    fn equal_rates_pass_through() {
+        let input = tone(440.0, 16000, 1000);
+        let mut resampler = Resampler::new(16000, 16000).unwrap();
+        let mut out = Vec::new();
+        resampler.process(&input, &mut out);
+        resampler.flush(&mut out);
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn zero_rates_are_rejected() {
+        assert!(Resampler::new(0, 16000).is_err());
+        assert!(Resampler::new(48000, 0).is_err());
+    }
+
+    #[test]
+    fn passband_tone_keeps_its_level() {
+        let out = resample(&tone(1000.0, 48000, 48000), 48000, 16000);
+        let level = middle_rms(&out) / (0.5 / 2f32.sqrt());
+        assert!((level - 1.0).abs() < 0.01, "level {}", level);
+    }
+
+    #[test]
+    fn tones_above_the_new_nyquist_are_removed() {
+        // 10 kHz can't be represented at 16 kHz and would alias to 6 kHz
+        let out = resample(&tone(10_000.0, 48000, 48000), 48000, 16000);
+        let level = middle_rms(&out) / (0.5 / 2f32.sqrt());
+        assert!(level < 0.001, "level {}", level);
+    }
+
+    #[test]
+    fn chunked_input_gives_the_same_output() {
+        let input: Vec<f32> = (0..44100).map(|i| ((i * 7919 % 1000) as f32 / 1000.0) - 0.5).collect();
+        let whole = resample(&input, 44100, 16000);
+
+        let mut resampler = Resampler::new(44100, 16000).unwrap();
+        let mut chunked = Vec::new();
+        let mut rest = &input[..];
+        for size in [1, 7, 160, 441, 1024].iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, tail) = rest.split_at((*size).min(rest.len()));
+            resampler.process(chunk, &mut chunked);
+            rest = tail;
+        }
+        resampler.flush(&mut chunked);
+
+        assert_eq!(chunked.len(), whole.len());
+        for (a, b) in chunked.iter().zip(&whole) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+}
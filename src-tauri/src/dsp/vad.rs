@@ -0,0 +1,270 @@
+// Energy-based voice activity detection on 16kHz mono audio
+// Cheap enough to run inside the capture callback. Tracks a slowly adapting noise
+// floor so a fan or open office doesn't count as speech.
+
+use super::WHISPER_SAMPLE_RATE;
+
+/// 30ms analysis frames
+pub const FRAME_LEN: usize = (WHISPER_SAMPLE_RATE as usize * 30) / 1000;
+const FRAME_MS: u64 = 30;
+
+/// Default absolute RMS a frame must exceed to count as speech
+pub const DEFAULT_THRESHOLD: f32 = 0.015;
+/// Speech must also stand this far above the noise floor
+const NOISE_RATIO: f32 = 3.0;
+/// Consecutive voiced frames needed before speech is considered started
+const ONSET_FRAMES: u32 = 3;
+
+pub struct EnergyVad {
+    threshold: f32,
+    /// Unset until the first frame, so digital silence (0.0) is a floor like any other
+    noise_floor: Option<f32>,
+    pending: Vec<f32>,
+    voiced_run: u32,
+    heard_speech: bool,
+    trailing_silence_ms: u64,
+    frames: u64,
+    voiced_frames: u64,
+}
+
+impl EnergyVad {
+    pub fn new(threshold: Option<f32>) -> Self {
+        Self {
+            threshold: threshold.unwrap_or(DEFAULT_THRESHOLD),
+            noise_floor: None,
+            pending: Vec::with_capacity(FRAME_LEN),
+            voiced_run: 0,
+            heard_speech: false,
+            trailing_silence_ms: 0,
+            frames: 0,
+            voiced_frames: 0,
+        }
+    }
+
+    /// Feed samples; returns true on the frame where speech first starts.
+    pub fn push(&mut self, samples: &[f32]) -> bool {
+        let mut started = false;
+        for &s in samples {
+            self.pending.push(s);
+            if self.pending.len() == FRAME_LEN {
+                let voiced = self.classify_frame();
+                self.pending.clear();
+                started |= self.update(voiced);
+            }
+        }
+        started
+    }
+
+    fn classify_frame(&mut self) -> bool {
+        let rms = frame_rms(&self.pending);
+
+        // Follow drops in level immediately, rises only slowly
+        let floor = match self.noise_floor {
+            Some(floor) if rms >= floor => floor + (rms - floor) * 0.005,
+            _ => rms,
+        };
+        self.noise_floor = Some(floor);
+
+        rms > self.threshold && rms > floor * NOISE_RATIO
+    }
+
+    fn update(&mut self, voiced: bool) -> bool {
+        self.frames += 1;
+        if voiced {
+            self.voiced_frames += 1;
+            self.voiced_run += 1;
+            self.trailing_silence_ms = 0;
+            if !self.heard_speech && self.voiced_run >= ONSET_FRAMES {
+                self.heard_speech = true;
+                return true;
+            }
+        } else {
+            self.voiced_run = 0;
+            self.trailing_silence_ms += FRAME_MS;
+        }
+        false
+    }
+
+    pub fn heard_speech(&self) -> bool {
+        self.heard_speech
+    }
+
+    /// Silence since the last voiced frame
+    pub fn trailing_silence_ms(&self) -> u64 {
+        self.trailing_silence_ms
+    }
+
+    /// Share of frames so far that counted as speech
+    pub fn speech_ratio(&self) -> f32 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+        self.voiced_frames as f32 / self.frames as f32
+    }
+}
+
+/// Share of a finished recording that is speech, with the default threshold
+pub fn speech_ratio(samples: &[f32]) -> f32 {
+    let mut vad = EnergyVad::new(None);
+    vad.push(samples);
+    vad.speech_ratio()
+}
+
+pub fn frame_rms(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+/// Where to split audio without cutting a word: the middle of its longest stretch of frames
+/// near the quietest level in it. Returns a sample index on a frame boundary.
+pub fn pause_midpoint(samples: &[f32]) -> usize {
+    let levels: Vec<f32> = samples.chunks_exact(FRAME_LEN).map(frame_rms).collect();
+    let floor = levels.iter().copied().fold(f32::INFINITY, f32::min);
+    let threshold = DEFAULT_THRESHOLD.max(floor * NOISE_RATIO);
+
+    // Longest run of quiet frames, the later one on a tie
+    let mut longest: Option<(usize, usize)> = None;
+    let mut run_start = None;
+    for (i, &level) in levels.iter().enumerate() {
+        if level >= threshold {
+            run_start = None;
+            continue;
+        }
+        let start = *run_start.get_or_insert(i);
+        if longest.is_none_or(|(_, len)| i + 1 - start >= len) {
+            longest = Some((start, i + 1 - start));
+        }
+    }
+
+    match longest {
+        Some((start, len)) => (start + len / 2) * FRAME_LEN,
+        None => samples.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(frames: usize, level: f32) -> Vec<f32> {
+        (0..frames * FRAME_LEN).map(|i| if i % 2 == 0 { level } else { -level }).collect()
+    }
+
+    #[test]
+    fn silence_is_not_speech() {
+        let mut vad = EnergyVad::new(None);
+        assert!(!vad.push(&tone(20, 0.001)));
+        assert!(!vad.heard_speech());
+        assert_eq!(vad.trailing_silence_ms(), 20 * FRAME_MS);
+        assert_eq!(vad.speech_ratio(), 0.0);
+    }
+
+    #[test]
+    fn speech_starts_after_the_onset_frames() {
+        let mut vad = EnergyVad::new(None);
+        vad.push(&tone(10, 0.001));
+        assert!(!vad.push(&tone(ONSET_FRAMES as usize - 1, 0.2)));
+        assert!(!vad.heard_speech());
+        assert!(vad.push(&tone(1, 0.2)));
+        assert!(vad.heard_speech());
+        // Reported once per take
+        assert!(!vad.push(&tone(5, 0.2)));
+    }
+
+    #[test]
+    fn speech_after_digital_silence_is_heard() {
+        let mut vad = EnergyVad::new(None);
+        vad.push(&tone(10, 0.0));
+        assert!(vad.push(&tone(ONSET_FRAMES as usize, 0.2)));
+    }
+
+    #[test]
+    fn quiet_sounds_stay_below_the_threshold() {
+        let mut vad = EnergyVad::new(None);
+        vad.push(&tone(10, 0.0001));
+        assert!(!vad.push(&tone(10, DEFAULT_THRESHOLD * 0.5)));
+        let mut strict = EnergyVad::new(Some(0.5));
+        strict.push(&tone(10, 0.001));
+        assert!(!strict.push(&tone(10, 0.2)));
+    }
+
+    #[test]
+    fn trailing_silence_counts_from_the_last_voiced_frame() {
+        let mut vad = EnergyVad::new(None);
+        vad.push(&tone(10, 0.001));
+        vad.push(&tone(10, 0.2));
+        assert_eq!(vad.trailing_silence_ms(), 0);
+        vad.push(&tone(20, 0.001));
+        assert_eq!(vad.trailing_silence_ms(), 20 * FRAME_MS);
+        // A short sound in the pause starts the count again without a new onset
+        assert!(!vad.push(&tone(1, 0.2)));
+        assert_eq!(vad.trailing_silence_ms(), 0);
+        vad.push(&tone(5, 0.001));
+        assert_eq!(vad.trailing_silence_ms(), 5 * FRAME_MS);
+        assert!((vad.speech_ratio() - 11.0 / 46.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn partial_frames_wait_for_the_rest() {
+        let mut vad = EnergyVad::new(None);
+        vad.push(&tone(1, 0.001)[..FRAME_LEN / 2]);
+        assert_eq!(vad.trailing_silence_ms(), 0);
+        vad.push(&tone(1, 0.001)[..FRAME_LEN / 2]);
+        assert_eq!(vad.trailing_silence_ms(), FRAME_MS);
+    }
+
+    #[test]
+    fn splits_in_the_longest_pause() {
+        let mut samples = tone(10, 0.2);
+        samples.extend(tone(2, 0.001));
+        samples.extend(tone(10, 0.2));
+        samples.extend(tone(6, 0.001));
+        samples.extend(tone(4, 0.2));
+        assert_eq!(pause_midpoint(&samples), (10 + 2 + 10 + 3) * FRAME_LEN);
+    }
+
+    #[test]
+    fn without_a_pause_splits_at_the_quietest_frames() {
+        let mut samples = tone(5, 0.5);
+        samples.extend(tone(3, 0.1));
+        samples.extend(tone(5, 0.5));
+        assert_eq!(pause_midpoint(&samples), 6 * FRAME_LEN);
+    }
+
+    #[test]
+    fn audio_shorter_than_a_frame_is_not_split() {
+        let samples = tone(1, 0.2);
+        assert_eq!(pause_midpoint(&samples[..FRAME_LEN - 1]), FRAME_LEN - 1);
+    }
+
+    /// Deterministic white noise in [-amplitude, amplitude]
+    fn noise(frames: usize, amplitude: f32) -> Vec<f32> {
+        let mut state = 0x2545_f491u32;
+        (0..frames * FRAME_LEN)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    #[test]
+    fn speech_ratio_counts_the_voiced_share() {
+        assert_eq!(speech_ratio(&tone(30, 0.0)), 0.0);
+        assert_eq!(speech_ratio(&[]), 0.0);
+        let mut samples = tone(20, 0.001);
+        samples.extend(tone(20, 0.2));
+        samples.extend(tone(20, 0.001));
+        assert!((speech_ratio(&samples) - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn steady_noise_is_not_speech() {
+        assert_eq!(speech_ratio(&noise(100, 0.05)), 0.0);
+        assert_eq!(speech_ratio(&tone(100, 0.2)), 0.0);
+    }
+}
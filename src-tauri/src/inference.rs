@@ -0,0 +1,120 @@
+// Whisper runs with real progress and mid-inference cancellation.
+// whisper-rs' closure helpers for these callbacks hand whisper.cpp a pointer that does not
+// outlive the call (progress) or has the wrong type (abort), so the raw callbacks are set here
+// with user data that lives on this stack frame for the whole `full` call.
+
+use std::ffi::{c_int, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+use whisper_rs::{FullParams, WhisperState, WhisperSysContext, WhisperSysState};
+
+pub(crate) const CANCELLED: &str = "Cancelled by user";
+
+unsafe extern "C" fn on_progress(
+    _ctx: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    progress: c_int,
+    user_data: *mut c_void,
+) {
+    let callback = &mut *(user_data as *mut &mut dyn FnMut(i32));
+    callback(progress.clamp(0, 100));
+}
+
+// Polled from the compute threads, so it only ever reads the flag
+unsafe extern "C" fn should_abort(user_data: *mut c_void) -> bool {
+    (*(user_data as *const AtomicBool)).load(Ordering::SeqCst)
+}
+
+fn check_cancelled(cancel: &AtomicBool) -> Result<(), String> {
+    if cancel.load(Ordering::SeqCst) {
+        return Err(CANCELLED.to_string());
+    }
+    Ok(())
+}
+
+/// Run `state.full`, passing whisper's own percentage (0-100) to `progress` and stopping
+/// as soon as `cancel` is set. A cancelled run fails with `CANCELLED`.
+pub(crate) fn full_with_progress(
+    state: &mut WhisperState,
+    mut params: FullParams,
+    samples: &[f32],
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(i32),
+) -> Result<(), String> {
+    check_cancelled(cancel)?;
+    let mut callback: &mut dyn FnMut(i32) = &mut progress;
+    // SAFETY: `callback` and `cancel` outlive `full`, which is the only time whisper.cpp
+    // calls back; the abort callback only gets a shared reference to the atomic.
+    unsafe {
+        params.set_progress_callback(Some(on_progress));
+        params.set_progress_callback_user_data(&mut callback as *mut &mut dyn FnMut(i32) as *mut c_void);
+        params.set_abort_callback(Some(should_abort));
+        params.set_abort_callback_user_data(cancel as *const AtomicBool as *mut c_void);
+    }
+    let result = state.full(params, samples);
+    check_cancelled(cancel)?;
+    result.map_err(|e| format!("Failed to run model: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use whisper_rs::{SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+    #[test]
+    fn abort_callback_follows_the_flag() {
+        let cancel = AtomicBool::new(false);
+        let user_data = &cancel as *const AtomicBool as *mut c_void;
+        // SAFETY: `user_data` points at `cancel`, as `full_with_progress` sets it up
+        assert!(!unsafe { should_abort(user_data) });
+        cancel.store(true, Ordering::SeqCst);
+        assert!(unsafe { should_abort(user_data) });
+        assert_eq!(check_cancelled(&cancel), Err(CANCELLED.to_string()));
+    }
+
+    #[test]
+    fn progress_callback_reports_percentages() {
+        let mut reported = Vec::new();
+        let mut progress = |p: i32| reported.push(p);
+        let mut callback: &mut dyn FnMut(i32) = &mut progress;
+        let user_data = &mut callback as *mut &mut dyn FnMut(i32) as *mut c_void;
+        for p in [-5, 0, 42, 100, 130] {
+            // SAFETY: `user_data` points at `callback`, as `full_with_progress` sets it up
+            unsafe { on_progress(std::ptr::null_mut(), std::ptr::null_mut(), p, user_data) };
+        }
+        assert_eq!(reported, vec![0, 0, 42, 100, 100]);
+    }
+
+    #[test]
+    #[ignore = "needs a model: WHISPER_TEST_MODEL=path/to/ggml-tiny.bin cargo test -- --ignored"]
+    fn cancelling_stops_a_long_run_quickly() {
+        let model = std::env::var("WHISPER_TEST_MODEL").expect("WHISPER_TEST_MODEL is not set");
+        let ctx = WhisperContext::new_with_params(&model, WhisperContextParameters::default()).unwrap();
+        let mut state = ctx.create_state().unwrap();
+
+        // Half an hour of tone, far more than the test waits for
+        let samples: Vec<f32> = (0..16000 * 1800).map(|i| (i as f32 * 0.05).sin() * 0.1).collect();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let canceller = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_secs(3));
+                cancel.store(true, Ordering::SeqCst);
+                Instant::now()
+            })
+        };
+
+        let mut reported = Vec::new();
+        let params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        let result = full_with_progress(&mut state, params, &samples, &cancel, |p| reported.push(p));
+        let stopped = Instant::now();
+        let cancelled = canceller.join().unwrap();
+
+        assert_eq!(result, Err(CANCELLED.to_string()));
+        let lag = stopped.saturating_duration_since(cancelled);
+        assert!(lag < Duration::from_secs(2), "took {:?} to stop after cancelling", lag);
+        assert!(reported.windows(2).all(|w| w[0] <= w[1]), "progress went backwards: {:?}", reported);
+        assert!(reported.iter().all(|&p| p < 100), "finished before it was cancelled: {:?}", reported);
+    }
+}
@@ -1,5 +1,12 @@
+mod chunked;
 mod commands;
+mod dsp;
+mod inference;
+mod media;
+mod postprocess;
 mod state;
+mod storage;
+mod transcript;
 
 use state::AudioState;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -12,8 +19,8 @@ use tauri::{
 // Track if app was launched via autostart
 static LAUNCHED_VIA_AUTOSTART: AtomicBool = AtomicBool::new(false);
 
-// Track if SRT/transcription should be cancelled
-pub static SRT_CANCELLED: AtomicBool = AtomicBool::new(false);
+// Set by `cancel_transcription`, stops the running Whisper pass
+pub static TRANSCRIPTION_CANCELLED: AtomicBool = AtomicBool::new(false);
 
 #[tauri::command]
 fn was_autostarted() -> bool {
@@ -22,7 +29,7 @@ fn was_autostarted() -> bool {
 
 #[tauri::command]
 fn cancel_transcription() -> bool {
-    SRT_CANCELLED.store(true, Ordering::SeqCst);
+    TRANSCRIPTION_CANCELLED.store(true, Ordering::SeqCst);
     true
 }
 
@@ -107,8 +114,14 @@ pub fn run() {
         .manage(state::WhisperModelState::new())
         .manage(commands::manager::DownloadState::new())
         .invoke_handler(tauri::generate_handler![
+            commands::audio::list_input_devices,
+            commands::audio::set_warm_mic,
+            commands::audio::get_recording_limit,
             commands::audio::start_recording,
             commands::audio::stop_recording,
+            commands::audio::discard_recording,
+            commands::audio::pause_recording,
+            commands::audio::resume_recording,
             commands::system::open_link,
             commands::system::open_folder,
             commands::system::paste_text,
@@ -119,11 +132,28 @@ pub fn run() {
             commands::sounds::play_start_sound,
             commands::sounds::play_end_sound,
             commands::local::transcribe_local,
+            commands::local::transcribe_recording,
+            commands::live::finish_live_transcription,
             commands::manager::check_model_exists,
             commands::manager::download_model,
             commands::manager::delete_model,
             commands::manager::cancel_download,
             commands::srt::generate_srt,
+            commands::vocabulary::list_vocabularies,
+            commands::vocabulary::save_vocabulary,
+            commands::vocabulary::delete_vocabulary,
+            commands::vocabulary::get_vocabulary_prompt,
+            commands::rules::list_replacement_rules,
+            commands::rules::save_replacement_rules,
+            commands::rules::import_replacement_rules,
+            commands::rules::export_replacement_rules,
+            commands::filter::get_hallucination_filter,
+            commands::filter::save_hallucination_filter,
+            commands::filter::reset_hallucination_filter,
+            commands::spoken::get_spoken_commands,
+            commands::spoken::save_spoken_commands,
+            commands::spoken::reset_spoken_commands,
+            commands::media::probe_media,
             commands::system::set_tray_icon,
             was_autostarted,
             cancel_transcription
@@ -0,0 +1,390 @@
+// Opening media files as 16kHz mono sample streams.
+// PCM WAV goes through hound, the common audio formats and Opus are decoded in-process with
+// symphonia, and only what it can't read falls back to the ffmpeg binary.
+
+mod opus;
+pub mod probe;
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecRegistry, Decoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
+use crate::dsp::{self, downmix, resample::Resampler, SampleStream, WavStream, WHISPER_SAMPLE_RATE};
+use probe::{DecodePath, StreamInfo};
+
+/// Symphonia's codecs plus Opus
+pub(crate) fn codecs() -> &'static CodecRegistry {
+    static CODECS: OnceLock<CodecRegistry> = OnceLock::new();
+    CODECS.get_or_init(|| {
+        let mut registry = CodecRegistry::new();
+        symphonia::default::register_enabled_codecs(&mut registry);
+        registry.register_all::<opus::OpusDecoder>();
+        registry
+    })
+}
+
+/// Part of a file's timeline, in ms from its start
+#[derive(Clone, Copy, Default)]
+pub struct TimeRange {
+    pub start_ms: u64,
+    /// To the end of the file when unset
+    pub end_ms: Option<u64>,
+}
+
+impl TimeRange {
+    pub fn new(start_ms: Option<u64>, end_ms: Option<u64>) -> Result<Self, String> {
+        let start_ms = start_ms.unwrap_or(0);
+        if end_ms.is_some_and(|end| end <= start_ms) {
+            return Err("The end of the range must come after its start".to_string());
+        }
+        Ok(Self { start_ms, end_ms })
+    }
+}
+
+/// The audio of one stream of a media file
+pub struct Opened {
+    pub audio: Box<dyn SampleStream>,
+    pub track: StreamInfo,
+}
+
+/// Open an audio stream of a media file, the first one unless `stream` names another,
+/// with the decoder its probe picked. Only `range` of it is decoded.
+pub fn open(path: &str, stream: Option<usize>, range: TimeRange) -> Result<Opened, String> {
+    if !Path::new(path).exists() {
+        return Err(format!("Input file not found: {}", path));
+    }
+    let info = probe::probe(path)?;
+    let track = info.audio_stream(stream)?.clone();
+    let decoder = info.decoder_for(track.index);
+    println!("[Media] {} stream {} decoded via {:?}", info.container, track.index, decoder);
+
+    let full_ms = track.duration_ms.or(info.duration_ms);
+    if full_ms.is_some_and(|full| range.start_ms >= full) {
+        return Err("The range starts after the end of the file".to_string());
+    }
+    let audio: Box<dyn SampleStream> = match decoder {
+        DecodePath::Wav => {
+            let mut wav = WavStream::open(path)?;
+            wav.seek(range.start_ms)?;
+            Box::new(wav)
+        }
+        DecodePath::Native => Box::new(DecodedStream::open(path, track.index, range.start_ms)?),
+        DecodePath::Ffmpeg => match ConvertedStream::open(path, track.index, range) {
+            Ok(converted) => Box::new(converted),
+            Err(e) => return Err(format!("{} audio needs ffmpeg. {}", track.codec.as_deref().unwrap_or("This"), e)),
+        },
+    };
+    let end_ms = match (range.end_ms, full_ms) {
+        (Some(end), Some(full)) => Some(end.min(full)),
+        (end, full) => end.or(full),
+    };
+    let duration_ms = end_ms.map_or(audio.duration_ms(), |end| end - range.start_ms);
+    let remaining = range.end_ms.map_or(usize::MAX, |end| dsp::ms_to_samples(end - range.start_ms));
+    Ok(Opened { audio: Box::new(Sliced { inner: audio, remaining, duration_ms }), track })
+}
+
+/// A stream cut off after the end of a range
+struct Sliced {
+    inner: Box<dyn SampleStream>,
+    /// Samples left before the end
+    remaining: usize,
+    duration_ms: u64,
+}
+
+impl SampleStream for Sliced {
+    fn duration_ms(&self) -> u64 {
+        self.duration_ms
+    }
+
+    fn read(&mut self, max: usize, output: &mut Vec<f32>) -> Result<usize, String> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+        let before = output.len();
+        let read = self.inner.read(max.min(self.remaining), output)?.min(self.remaining);
+        output.truncate(before + read);
+        self.remaining -= read;
+        Ok(read)
+    }
+}
+
+/// An audio file decoded in-process
+struct DecodedStream {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    duration_ms: u64,
+    /// Output still to drop, from where the seek landed up to the requested start
+    skip: usize,
+    /// Created from the first decoded packet, which knows the real rate
+    resampler: Option<Resampler>,
+    interleaved: Option<SampleBuffer<f32>>,
+    mono: Vec<f32>,
+    finished: bool,
+}
+
+impl DecodedStream {
+    fn open(path: &str, index: usize, start_ms: u64) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        let source = MediaSourceStream::new(Box::new(file), Default::default());
+        let probed = symphonia::default::get_probe()
+            .format(&Hint::new(), source, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| format!("Unsupported format: {}", e))?;
+        let mut format = probed.format;
+
+        let track = format
+            .tracks()
+            .get(index)
+            .filter(|track| track.codec_params.sample_rate.is_some())
+            .ok_or_else(|| format!("Stream {} is not an audio stream", index))?;
+        let decoder = codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| format!("Unsupported codec: {}", e))?;
+        let track_id = track.id;
+        let params = track.codec_params.clone();
+        let duration_ms = probe::track_duration_ms(&params).unwrap_or(0);
+
+        // Seeking lands on a packet at or before the start, the rest is decoded and dropped.
+        // Files that can't seek are decoded from the beginning instead.
+        let mut skip = dsp::ms_to_samples(start_ms);
+        if start_ms > 0 {
+            let time = Time::new(start_ms / 1000, (start_ms % 1000) as f64 / 1000.0);
+            match format.seek(SeekMode::Accurate, SeekTo::Time { time, track_id: Some(track_id) }) {
+                Ok(seeked) => {
+                    let landed = probe::ts_to_seconds(&params, seeked.actual_ts)
+                        .map_or(skip, |seconds| (seconds * WHISPER_SAMPLE_RATE as f64).round() as usize);
+                    skip = skip.saturating_sub(landed);
+                }
+                Err(e) => eprintln!("[Media] Seek failed, decoding from the start: {}", e),
+            }
+        }
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            duration_ms,
+            skip,
+            resampler: None,
+            interleaved: None,
+            mono: Vec::new(),
+            finished: false,
+        })
+    }
+
+    fn finish(&mut self, output: &mut Vec<f32>) {
+        if let Some(resampler) = &mut self.resampler {
+            resampler.flush(output);
+        }
+        self.finished = true;
+    }
+}
+
+impl SampleStream for DecodedStream {
+    fn duration_ms(&self) -> u64 {
+        self.duration_ms
+    }
+
+    fn read(&mut self, max: usize, output: &mut Vec<f32>) -> Result<usize, String> {
+        let before = output.len();
+        while output.len() - before < max && !self.finished {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    self.finish(output);
+                    break;
+                }
+                // A new chained stream, which can change format; the first one is enough
+                Err(SymphoniaError::ResetRequired) => {
+                    self.finish(output);
+                    break;
+                }
+                Err(e) => return Err(format!("Failed to read audio: {}", e)),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(SymphoniaError::DecodeError(e)) => {
+                    eprintln!("[Media] Skipped a corrupt packet: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+            };
+            let spec = *decoded.spec();
+            let channels = spec.channels.count();
+            let needed = decoded.capacity() * channels;
+            if self.interleaved.as_ref().is_none_or(|buf| buf.capacity() < needed) {
+                self.interleaved = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+            }
+            let interleaved = self.interleaved.as_mut().unwrap();
+            interleaved.copy_interleaved_ref(decoded);
+
+            self.mono.clear();
+            downmix(interleaved.samples(), channels, &mut self.mono);
+            let produced = output.len();
+            let resampler = match &mut self.resampler {
+                Some(resampler) => resampler,
+                None => self.resampler.insert(Resampler::new(spec.rate, WHISPER_SAMPLE_RATE)?),
+            };
+            resampler.process(&self.mono, output);
+            if self.skip > 0 {
+                let dropped = self.skip.min(output.len() - produced);
+                output.drain(produced..produced + dropped);
+                self.skip -= dropped;
+            }
+        }
+        Ok(output.len() - before)
+    }
+}
+
+/// Deleted when dropped
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// ffmpeg's 16kHz WAV conversion of a range of the file, removed once the stream is dropped
+struct ConvertedStream {
+    wav: WavStream,
+    // Declared after `wav` so the file is closed before it is deleted
+    _temp: TempFile,
+}
+
+impl ConvertedStream {
+    fn open(path: &str, index: usize, range: TimeRange) -> Result<Self, String> {
+        let temp = TempFile(std::env::temp_dir().join(format!("{}.wav", crate::storage::new_id("whisper"))));
+        convert_to_wav(path, index, range, &temp.0)?;
+        Ok(Self { wav: WavStream::open(&temp.0.to_string_lossy())?, _temp: temp })
+    }
+}
+
+impl SampleStream for ConvertedStream {
+    fn duration_ms(&self) -> u64 {
+        self.wav.duration_ms()
+    }
+
+    fn read(&mut self, max: usize, output: &mut Vec<f32>) -> Result<usize, String> {
+        self.wav.read(max, output)
+    }
+}
+
+fn ffmpeg_time(ms: u64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+/// Convert a range of one audio stream of any media file to 16kHz mono WAV using ffmpeg
+fn convert_to_wav(input_path: &str, stream: usize, range: TimeRange, output_path: &Path) -> Result<(), String> {
+    let mut command = Command::new("ffmpeg");
+    command.arg("-y"); // Overwrite output
+    // Before the input, so ffmpeg seeks instead of decoding up to the start
+    if range.start_ms > 0 {
+        command.arg("-ss").arg(ffmpeg_time(range.start_ms));
+    }
+    command.arg("-i").arg(input_path); // Input path as single arg (handles spaces)
+    if let Some(end_ms) = range.end_ms {
+        command.arg("-t").arg(ffmpeg_time(end_ms - range.start_ms));
+    }
+    let status = command
+        .arg("-map").arg(format!("0:{}", stream)) // Only the chosen stream
+        .arg("-ac").arg("1") // Mono
+        .arg("-ar").arg("16000") // 16kHz sample rate
+        .arg("-acodec").arg("pcm_s16le") // 16-bit PCM
+        .arg("-f").arg("wav")
+        .arg(output_path)    // Output path as single arg
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg: {}. Is ffmpeg installed?", e))?;
+
+    if !status.status.success() {
+        let stderr = String::from_utf8_lossy(&status.stderr);
+        return Err(format!("ffmpeg conversion failed: {}", stderr));
+    }
+
+    // Verify output was created
+    if !output_path.exists() {
+        return Err("ffmpeg completed but output file was not created".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts up from 0 in whole chunks, whatever `max` asks for, like a packet decoder
+    struct Chunked {
+        next: usize,
+        len: usize,
+        chunk: usize,
+    }
+
+    impl SampleStream for Chunked {
+        fn duration_ms(&self) -> u64 {
+            0
+        }
+
+        fn read(&mut self, _max: usize, output: &mut Vec<f32>) -> Result<usize, String> {
+            let end = (self.next + self.chunk).min(self.len);
+            output.extend((self.next..end).map(|i| i as f32));
+            let read = end - self.next;
+            self.next = end;
+            Ok(read)
+        }
+    }
+
+    fn slice(len: usize, chunk: usize, remaining: usize) -> Vec<f32> {
+        let mut sliced = Sliced { inner: Box::new(Chunked { next: 0, len, chunk }), remaining, duration_ms: 0 };
+        let mut output = vec![-1.0];
+        while sliced.read(64, &mut output).unwrap() > 0 {}
+        output
+    }
+
+    #[test]
+    fn ranges_must_end_after_they_start() {
+        assert!(TimeRange::new(Some(5000), Some(1000)).is_err());
+        assert!(TimeRange::new(Some(1000), Some(1000)).is_err());
+        assert!(TimeRange::new(None, Some(0)).is_err());
+
+        let range = TimeRange::new(Some(1000), Some(5000)).unwrap();
+        assert_eq!((range.start_ms, range.end_ms), (1000, Some(5000)));
+        let open_ended = TimeRange::new(Some(1000), None).unwrap();
+        assert_eq!((open_ended.start_ms, open_ended.end_ms), (1000, None));
+        assert_eq!(TimeRange::new(None, None).unwrap().start_ms, 0);
+    }
+
+    #[test]
+    fn slices_end_mid_chunk() {
+        // The chunk that crosses the end is cut, earlier output is left alone
+        let output = slice(1000, 100, 250);
+        assert_eq!(output.len(), 251);
+        assert_eq!(output[0], -1.0);
+        assert_eq!(output[250], 249.0);
+    }
+
+    #[test]
+    fn slices_end_on_a_chunk_boundary() {
+        assert_eq!(slice(1000, 100, 300).len(), 301);
+        assert_eq!(slice(1000, 100, 0), vec![-1.0]);
+    }
+
+    #[test]
+    fn open_ended_slices_read_everything() {
+        let output = slice(1000, 64, usize::MAX);
+        assert_eq!(output.len(), 1001);
+        assert_eq!(output[1000], 999.0);
+    }
+}
@@ -0,0 +1,101 @@
+// Opus decoding for symphonia, which demuxes Opus streams but ships no decoder for them.
+// Backed by libopus through audiopus.
+
+use audiopus::coder::{Decoder as LibOpus, GenericCtl};
+use audiopus::{packet::Packet as OpusPacket, Channels, MutSignals, SampleRate};
+use symphonia::core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia::core::codecs::{
+    CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult, CODEC_TYPE_OPUS,
+};
+use symphonia::core::errors::{decode_error, unsupported_error, Result};
+use symphonia::core::formats::Packet;
+use symphonia::core::support_codec;
+
+/// Opus always decodes at 48kHz, whatever rate the stream was recorded at
+const RATE: u32 = 48_000;
+/// The longest packet Opus allows is 120 ms
+const MAX_FRAMES: usize = RATE as usize * 120 / 1000;
+
+pub struct OpusDecoder {
+    opus: LibOpus,
+    params: CodecParameters,
+    channels: usize,
+    /// libopus' interleaved output, split into `buf` afterwards
+    interleaved: Vec<f32>,
+    buf: AudioBuffer<f32>,
+}
+
+// SAFETY: libopus decoder state is only touched through `&mut self`; shared references
+// never reach it.
+unsafe impl Sync for OpusDecoder {}
+
+impl Decoder for OpusDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let layout = match params.channels {
+            Some(layout) => layout,
+            None => return unsupported_error("opus: unknown channel count"),
+        };
+        // Surround streams need the multistream decoder, ffmpeg handles those
+        let channels = match layout.count() {
+            1 => Channels::Mono,
+            2 => Channels::Stereo,
+            _ => return unsupported_error("opus: more than two channels"),
+        };
+        let opus = match LibOpus::new(SampleRate::Hz48000, channels) {
+            Ok(opus) => opus,
+            Err(_) => return unsupported_error("opus: failed to create decoder"),
+        };
+        Ok(Self {
+            opus,
+            params: params.clone(),
+            channels: layout.count(),
+            interleaved: vec![0.0; MAX_FRAMES * layout.count()],
+            buf: AudioBuffer::new(MAX_FRAMES as u64, SignalSpec::new(RATE, layout)),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_OPUS, "opus", "Opus")]
+    }
+
+    fn reset(&mut self) {
+        let _ = self.opus.reset_state();
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buf.clear();
+        let input = match OpusPacket::try_from(packet.buf()) {
+            Ok(input) => input,
+            Err(_) => return decode_error("opus: empty packet"),
+        };
+        let output = match MutSignals::try_from(&mut self.interleaved[..]) {
+            Ok(output) => output,
+            Err(_) => return decode_error("opus: no output buffer"),
+        };
+        let frames = match self.opus.decode_float(Some(input), output, false) {
+            Ok(frames) => frames,
+            Err(_) => return decode_error("opus: invalid packet"),
+        };
+
+        self.buf.render_reserved(Some(frames));
+        for ch in 0..self.channels {
+            let plane = self.buf.chan_mut(ch);
+            for (i, sample) in plane.iter_mut().enumerate() {
+                *sample = self.interleaved[i * self.channels + ch];
+            }
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
@@ -0,0 +1,372 @@
+// What a media file holds, found from its content rather than its name, and how its
+// audio will be decoded.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use symphonia::core::codecs::{CodecParameters, CodecType, DecoderOptions};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use crate::commands::language;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamKind {
+    Audio,
+    Video,
+    Subtitle,
+    Other,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodePath {
+    /// PCM WAV read as it is
+    Wav,
+    /// Decoded in-process
+    Native,
+    /// Converted by the ffmpeg binary first
+    Ffmpeg,
+}
+
+#[derive(Clone, Serialize)]
+pub struct StreamInfo {
+    /// Position in the container, as ffmpeg numbers streams
+    pub index: usize,
+    pub kind: StreamKind,
+    pub codec: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub duration_ms: Option<u64>,
+    /// Language tag of the track, when the container has one
+    pub language: Option<String>,
+    /// Whisper code for `language`, the default transcription language of the track
+    pub language_code: Option<&'static str>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MediaInfo {
+    /// Recognised from the first bytes: "wav", "mp3", "mp4", "matroska", ... or "unknown"
+    pub container: String,
+    pub duration_ms: Option<u64>,
+    /// Of the audio stream that gets transcribed
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub has_video: bool,
+    pub streams: Vec<StreamInfo>,
+    /// How the default audio stream will be decoded
+    pub decoder: DecodePath,
+    /// Indexes of the streams symphonia can decode
+    #[serde(skip)]
+    native: Vec<usize>,
+    #[serde(skip)]
+    wav: bool,
+}
+
+/// Container named by the first bytes of a file
+fn sniff(head: &[u8]) -> Option<&'static str> {
+    match head {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("wav"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'A', b'V', b'I', b' ', ..] => Some("avi"),
+        [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', ..] => Some("aiff"),
+        [b'f', b'L', b'a', b'C', ..] => Some("flac"),
+        [b'O', b'g', b'g', b'S', ..] => Some("ogg"),
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => Some("matroska"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'M', b'4', b'A', ..] => Some("m4a"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'q', b't', ..] => Some("mov"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("mp4"),
+        [b'I', b'D', b'3', ..] => Some("mp3"),
+        // Frame sync of raw ADTS, then of any other MPEG audio layer
+        [0xFF, b, ..] if b & 0xF6 == 0xF0 => Some("aac"),
+        [0xFF, b, ..] if b & 0xE0 == 0xE0 => Some("mp3"),
+        _ => None,
+    }
+}
+
+fn can_hold_video(container: &str) -> bool {
+    matches!(container, "avi" | "matroska" | "mov" | "mp4")
+}
+
+fn codec_name(codec: CodecType) -> Option<String> {
+    super::codecs().get_codec(codec).map(|d| d.short_name.to_string())
+}
+
+/// A timestamp of a symphonia track in seconds
+pub(super) fn ts_to_seconds(params: &CodecParameters, ts: u64) -> Option<f64> {
+    match (params.time_base, params.sample_rate) {
+        (Some(base), _) => {
+            let time = base.calc_time(ts);
+            Some(time.seconds as f64 + time.frac)
+        }
+        (None, Some(rate)) if rate > 0 => Some(ts as f64 / rate as f64),
+        _ => None,
+    }
+}
+
+/// Length of a symphonia track, when the container says
+pub(super) fn track_duration_ms(params: &CodecParameters) -> Option<u64> {
+    ts_to_seconds(params, params.n_frames?).map(|seconds| (seconds * 1000.0) as u64)
+}
+
+/// Streams as symphonia reads them, and the indexes of those it can decode.
+/// It only describes audio tracks; in a container that can hold video the rest are taken to be video.
+fn probe_native(path: &str, container: &str) -> Result<(Vec<StreamInfo>, Vec<usize>), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+    let format = symphonia::default::get_probe()
+        .format(&Hint::new(), source, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Unsupported format: {}", e))?
+        .format;
+
+    let streams = format
+        .tracks()
+        .iter()
+        .enumerate()
+        .map(|(index, track)| {
+            let params = &track.codec_params;
+            let kind = if params.sample_rate.is_some() {
+                StreamKind::Audio
+            } else if can_hold_video(container) {
+                StreamKind::Video
+            } else {
+                StreamKind::Other
+            };
+            StreamInfo {
+                index,
+                kind,
+                codec: codec_name(params.codec),
+                sample_rate: params.sample_rate,
+                channels: params.channels.map(|c| c.count() as u32),
+                duration_ms: track_duration_ms(params),
+                language: track.language.clone(),
+                language_code: track.language.as_deref().and_then(language::from_track_tag),
+            }
+        })
+        .collect();
+    let decodable = format
+        .tracks()
+        .iter()
+        .enumerate()
+        .filter(|(_, track)| track.codec_params.sample_rate.is_some())
+        .filter(|(_, track)| super::codecs().make(&track.codec_params, &DecoderOptions::default()).is_ok())
+        .map(|(index, _)| index)
+        .collect();
+    Ok((streams, decodable))
+}
+
+#[derive(Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+    format: Option<FfprobeFormat>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeStream {
+    index: usize,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    disposition: HashMap<String, i64>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeFormat {
+    duration: Option<String>,
+}
+
+fn seconds_to_ms(seconds: &str) -> Option<u64> {
+    seconds.parse::<f64>().ok().filter(|s| *s >= 0.0).map(|s| (s * 1000.0).round() as u64)
+}
+
+/// Streams and overall length as ffprobe reports them
+fn probe_ffprobe(path: &str) -> Result<(Vec<StreamInfo>, Option<u64>), String> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-print_format", "json", "-show_streams", "-show_format"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let parsed: FfprobeOutput = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Unexpected ffprobe output: {}", e))?;
+
+    let streams = parsed
+        .streams
+        .into_iter()
+        .map(|stream| {
+            let kind = match stream.codec_type.as_deref() {
+                Some("audio") => StreamKind::Audio,
+                // Cover art is stored as a one-frame video stream
+                Some("video") if stream.disposition.get("attached_pic") != Some(&1) => StreamKind::Video,
+                Some("subtitle") => StreamKind::Subtitle,
+                _ => StreamKind::Other,
+            };
+            StreamInfo {
+                index: stream.index,
+                kind,
+                codec: stream.codec_name,
+                sample_rate: stream.sample_rate.and_then(|r| r.parse().ok()),
+                channels: stream.channels,
+                duration_ms: stream.duration.as_deref().and_then(seconds_to_ms),
+                language_code: stream.tags.get("language").and_then(|l| language::from_track_tag(l)),
+                language: stream.tags.get("language").filter(|l| *l != "und").cloned(),
+            }
+        })
+        .collect();
+    let duration_ms = parsed.format.and_then(|f| f.duration).as_deref().and_then(seconds_to_ms);
+    Ok((streams, duration_ms))
+}
+
+/// Inspect a media file and pick how to decode it.
+pub fn probe(path: &str) -> Result<MediaInfo, String> {
+    let mut head = [0u8; 16];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut head))
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let container = sniff(&head[..read]).unwrap_or("unknown");
+
+    let native = probe_native(path, container);
+    // Symphonia can't tell video from other tracks it doesn't decode, ffprobe can
+    let (streams, duration_ms) = match &native {
+        Ok((streams, _)) if !can_hold_video(container) => (streams.clone(), None),
+        _ => match (probe_ffprobe(path), &native) {
+            (Ok(found), _) => found,
+            (Err(_), Ok((streams, _))) => (streams.clone(), None),
+            (Err(ffprobe), Err(native)) => return Err(format!("{}. {}", native, ffprobe)),
+        },
+    };
+
+    let mut info = MediaInfo {
+        container: container.to_string(),
+        duration_ms,
+        sample_rate: None,
+        channels: None,
+        has_video: streams.iter().any(|s| s.kind == StreamKind::Video),
+        streams,
+        decoder: DecodePath::Ffmpeg,
+        native: native.map(|(_, decodable)| decodable).unwrap_or_default(),
+        wav: container == "wav" && hound::WavReader::open(path).is_ok(),
+    };
+    let audio = info.audio_stream(None)?.clone();
+    info.duration_ms = info.duration_ms.or(audio.duration_ms);
+    info.sample_rate = audio.sample_rate;
+    info.channels = audio.channels;
+    info.decoder = info.decoder_for(audio.index);
+    Ok(info)
+}
+
+impl MediaInfo {
+    /// The audio stream at `index`, or the first one
+    pub fn audio_stream(&self, index: Option<usize>) -> Result<&StreamInfo, String> {
+        match index {
+            Some(index) => match self.streams.iter().find(|s| s.index == index) {
+                Some(stream) if stream.kind == StreamKind::Audio => Ok(stream),
+                Some(_) => Err(format!("Stream {} is not an audio stream", index)),
+                None => Err(format!("The file has no stream {}", index)),
+            },
+            None => self.streams.iter().find(|s| s.kind == StreamKind::Audio).ok_or_else(|| "The file has no audio".to_string()),
+        }
+    }
+
+    pub fn decoder_for(&self, index: usize) -> DecodePath {
+        if self.wav {
+            DecodePath::Wav
+        } else if self.native.contains(&index) {
+            DecodePath::Native
+        } else {
+            DecodePath::Ffmpeg
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn containers_are_recognised_by_content() {
+        assert_eq!(sniff(b"RIFF\x24\x00\x00\x00WAVEfmt "), Some("wav"));
+        assert_eq!(sniff(b"RIFF\x24\x00\x00\x00AVI LIST"), Some("avi"));
+        assert_eq!(sniff(b"\x00\x00\x00\x20ftypM4A \x00\x00"), Some("m4a"));
+        assert_eq!(sniff(b"\x00\x00\x00\x20ftypisom\x00\x00"), Some("mp4"));
+        assert_eq!(sniff(b"\x1A\x45\xDF\xA3\x9F\x42\x86\x81"), Some("matroska"));
+        assert_eq!(sniff(b"ID3\x04\x00\x00"), Some("mp3"));
+        assert_eq!(sniff(b"{\"name\": 1}"), None);
+    }
+
+    #[test]
+    fn mpeg_frame_sync_tells_mp3_from_adts() {
+        assert_eq!(sniff(&[0xFF, 0xFB, 0x90, 0x64]), Some("mp3"));
+        assert_eq!(sniff(&[0xFF, 0xF1, 0x50, 0x80]), Some("aac"));
+    }
+
+    /// One Ogg page holding `packet`
+    fn ogg_page(sequence: u32, header_type: u8, granule: u64, packet: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\0".to_vec();
+        page.push(header_type);
+        page.extend(granule.to_le_bytes());
+        page.extend(1u32.to_le_bytes());
+        page.extend(sequence.to_le_bytes());
+        page.extend([0; 4]);
+        let lacing: Vec<u8> = std::iter::repeat_n(255, packet.len() / 255).chain([(packet.len() % 255) as u8]).collect();
+        page.push(lacing.len() as u8);
+        page.extend(lacing);
+        page.extend(packet);
+        let crc = page.iter().fold(0u32, |crc, &byte| {
+            (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04C1_1DB7 } else { crc << 1 })
+        });
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    #[test]
+    fn opus_is_decoded_natively() {
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend(312u16.to_le_bytes());
+        head.extend(48000u32.to_le_bytes());
+        head.extend([0, 0, 0]);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(4u32.to_le_bytes());
+        tags.extend(b"test");
+        tags.extend(0u32.to_le_bytes());
+        let mut file = ogg_page(0, 0x02, 0, &head);
+        file.extend(ogg_page(1, 0x00, 0, &tags));
+        // A 20 ms silent CELT frame
+        file.extend(ogg_page(2, 0x04, 960, &[0xFC, 0xFF, 0xFE]));
+
+        let path = std::env::temp_dir().join(format!("probe_test_{}.opus", std::process::id()));
+        std::fs::write(&path, file).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let info = probe(&path);
+        let samples = super::super::open(&path, None, Default::default()).and_then(|mut opened| {
+            let mut samples = Vec::new();
+            while opened.audio.read(4096, &mut samples)? > 0 {}
+            Ok(samples)
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        let info = info.unwrap();
+        assert_eq!(info.container, "ogg");
+        assert_eq!(info.streams[0].codec.as_deref(), Some("opus"));
+        assert_eq!(info.decoder, DecodePath::Native);
+        // 20 ms at 16kHz
+        assert_eq!(samples.unwrap().len(), 320);
+    }
+
+    #[test]
+    fn ffprobe_seconds_become_ms() {
+        assert_eq!(seconds_to_ms("12.3456"), Some(12346));
+        assert_eq!(seconds_to_ms("N/A"), None);
+    }
+}
@@ -0,0 +1,190 @@
+// Drops what Whisper makes up rather than hears: non-speech annotations, stock phrases
+// it produces on silence, and segments its own scores say are not speech.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterRules {
+    pub enabled: bool,
+    /// Remove "[Music]", "♪" and notes like "(speaking foreign language)"
+    pub strip_annotations: bool,
+    /// Words that make a parenthesized note an annotation rather than dictated text
+    pub annotation_words: Vec<String>,
+    /// Segments that consist of nothing but one of these, ignoring case and punctuation
+    pub phrases: Vec<String>,
+    /// A known phrase is kept when Whisper heard it at least this clearly (mean token log probability)
+    pub phrase_keep_logprob: f32,
+    /// A segment is silence when its no-speech probability is above this...
+    pub no_speech_thold: f32,
+    /// ...and its mean token log probability below this
+    pub logprob_thold: f32,
+}
+
+impl Default for FilterRules {
+    fn default() -> Self {
+        let words = ["music", "applause", "laughter", "laughs", "silence", "speaking", "foreign", "inaudible", "noise"];
+        let phrases = [
+            "Subscribe to my channel",
+            "Please subscribe",
+            "Thank you for watching",
+            "Thanks for watching",
+            "Subtitles by the Amara.org community",
+            "Amara.org",
+            "Transcribed by",
+        ];
+        Self {
+            enabled: true,
+            strip_annotations: true,
+            annotation_words: words.iter().map(|w| w.to_string()).collect(),
+            phrases: phrases.iter().map(|p| p.to_string()).collect(),
+            phrase_keep_logprob: -0.2,
+            // Whisper's own silence rule
+            no_speech_thold: 0.6,
+            logprob_thold: -1.0,
+        }
+    }
+}
+
+/// What Whisper reported about a segment
+#[derive(Clone, Copy)]
+pub struct Scores {
+    pub avg_logprob: f32,
+    pub no_speech_prob: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterReason {
+    /// Nothing left after removing annotations
+    Annotation,
+    /// One of the configured phrases, not clearly heard
+    KnownPhrase,
+    /// Whisper's scores say there was no speech
+    NoSpeech,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    /// Keep the segment with this text, annotations removed
+    Keep(String),
+    Drop(FilterReason),
+}
+
+/// Lowercase words only, so "Thanks for watching!" equals "thanks for watching"
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl FilterRules {
+    fn strip_annotations(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(['[', '(']) {
+            let close = if rest[start..].starts_with('[') { ']' } else { ')' };
+            let end = match rest[start..].find(close) {
+                Some(len) => start + len,
+                None => break,
+            };
+            let inner = rest[start + 1..end].to_lowercase();
+            // Whisper only uses brackets for non-speech, parentheses can be dictated
+            let annotation = close == ']' || self.annotation_words.iter().any(|w| inner.contains(&w.to_lowercase()));
+            out.push_str(&rest[..start]);
+            if !annotation {
+                out.push_str(&rest[start..=end]);
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        out.replace(['♪', '♫'], " ").split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Decide on one segment. Without scores a known phrase is always dropped.
+    pub fn check(&self, text: &str, scores: Option<Scores>) -> Verdict {
+        if !self.enabled {
+            return Verdict::Keep(text.trim().to_string());
+        }
+        let cleaned = if self.strip_annotations {
+            self.strip_annotations(text)
+        } else {
+            text.trim().to_string()
+        };
+        if cleaned.is_empty() && !text.trim().is_empty() {
+            return Verdict::Drop(FilterReason::Annotation);
+        }
+
+        if let Some(Scores { avg_logprob, no_speech_prob: Some(no_speech) }) = scores {
+            if no_speech > self.no_speech_thold && avg_logprob < self.logprob_thold {
+                return Verdict::Drop(FilterReason::NoSpeech);
+            }
+        }
+
+        let normalized = normalize(&cleaned);
+        let clearly_heard = scores.is_some_and(|s| s.avg_logprob >= self.phrase_keep_logprob);
+        if !clearly_heard && self.phrases.iter().any(|p| normalize(p) == normalized) {
+            return Verdict::Drop(FilterReason::KnownPhrase);
+        }
+        Verdict::Keep(cleaned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(avg_logprob: f32, no_speech_prob: Option<f32>) -> Option<Scores> {
+        Some(Scores { avg_logprob, no_speech_prob })
+    }
+
+    #[test]
+    fn sentences_mentioning_a_phrase_are_kept() {
+        let rules = FilterRules::default();
+        let text = " Don't forget to subscribe to my channel for updates.";
+        assert_eq!(rules.check(text, None), Verdict::Keep(text.trim().to_string()));
+    }
+
+    #[test]
+    fn unclear_known_phrases_are_dropped() {
+        let rules = FilterRules::default();
+        assert_eq!(rules.check(" Thanks for watching!", scores(-0.6, None)), Verdict::Drop(FilterReason::KnownPhrase));
+        assert_eq!(rules.check("amara.org", None), Verdict::Drop(FilterReason::KnownPhrase));
+    }
+
+    #[test]
+    fn clearly_heard_known_phrases_are_kept() {
+        let rules = FilterRules::default();
+        assert_eq!(rules.check("Thanks for watching.", scores(-0.1, None)), Verdict::Keep("Thanks for watching.".to_string()));
+    }
+
+    #[test]
+    fn annotations_are_removed_and_empty_segments_dropped() {
+        let rules = FilterRules::default();
+        assert_eq!(rules.check(" [Music]", None), Verdict::Drop(FilterReason::Annotation));
+        assert_eq!(rules.check("♪ (upbeat music) ♪", None), Verdict::Drop(FilterReason::Annotation));
+        assert_eq!(rules.check("So [BLANK_AUDIO] anyway", None), Verdict::Keep("So anyway".to_string()));
+    }
+
+    #[test]
+    fn dictated_parentheses_are_kept() {
+        let rules = FilterRules::default();
+        assert_eq!(rules.check("The fee (about 5%) applies", None), Verdict::Keep("The fee (about 5%) applies".to_string()));
+    }
+
+    #[test]
+    fn silence_needs_both_scores() {
+        let rules = FilterRules::default();
+        assert_eq!(rules.check("Hmm.", scores(-1.4, Some(0.9))), Verdict::Drop(FilterReason::NoSpeech));
+        assert_eq!(rules.check("Hmm.", scores(-0.4, Some(0.9))), Verdict::Keep("Hmm.".to_string()));
+        assert_eq!(rules.check("Hmm.", scores(-1.4, None)), Verdict::Keep("Hmm.".to_string()));
+    }
+
+    #[test]
+    fn disabled_filter_only_trims() {
+        let rules = FilterRules { enabled: false, ..FilterRules::default() };
+        assert_eq!(rules.check(" [Music]", None), Verdict::Keep("[Music]".to_string()));
+    }
+}
@@ -0,0 +1,97 @@
+// Text clean-up applied to transcripts after Whisper is done with them
+
+pub mod filter;
+pub mod rules;
+pub mod spoken;
+
+use tauri::{AppHandle, Runtime};
+use crate::storage;
+use crate::transcript::{FilteredSegment, TranscriptSegment};
+use filter::{FilterRules, Scores, Verdict};
+use rules::{ReplacementRule, RuleSet};
+use spoken::SpokenCommands;
+
+const RULES_FILE: &str = "replacement_rules.json";
+const FILTER_FILE: &str = "hallucination_filter.json";
+const SPOKEN_FILE: &str = "spoken_commands.json";
+
+pub(crate) fn load_rules<R: Runtime>(app: &AppHandle<R>) -> Result<Vec<ReplacementRule>, String> {
+    storage::load_json(app, RULES_FILE)
+}
+
+pub(crate) fn save_rules<R: Runtime>(app: &AppHandle<R>, rules: &[ReplacementRule]) -> Result<(), String> {
+    storage::save_json(app, RULES_FILE, rules)
+}
+
+/// The saved rules, compiled. A broken store never fails a transcription,
+/// the text just comes back unchanged.
+pub(crate) fn rule_set<R: Runtime>(app: &AppHandle<R>) -> RuleSet {
+    match load_rules(app).and_then(|rules| RuleSet::compile(&rules)) {
+        Ok(set) => set,
+        Err(e) => {
+            eprintln!("[Rules] {}", e);
+            RuleSet::default()
+        }
+    }
+}
+
+pub(crate) fn load_filter<R: Runtime>(app: &AppHandle<R>) -> Result<FilterRules, String> {
+    storage::load_json(app, FILTER_FILE)
+}
+
+pub(crate) fn save_filter<R: Runtime>(app: &AppHandle<R>, rules: &FilterRules) -> Result<(), String> {
+    storage::save_json(app, FILTER_FILE, rules)
+}
+
+/// The saved hallucination filter, or the built-in one if it can't be read
+pub(crate) fn filter_rules<R: Runtime>(app: &AppHandle<R>) -> FilterRules {
+    match load_filter(app) {
+        Ok(rules) => rules,
+        Err(e) => {
+            eprintln!("[Filter] {}", e);
+            FilterRules::default()
+        }
+    }
+}
+
+pub(crate) fn load_spoken<R: Runtime>(app: &AppHandle<R>) -> Result<SpokenCommands, String> {
+    storage::load_json(app, SPOKEN_FILE)
+}
+
+pub(crate) fn save_spoken<R: Runtime>(app: &AppHandle<R>, commands: &SpokenCommands) -> Result<(), String> {
+    storage::save_json(app, SPOKEN_FILE, commands)
+}
+
+/// The saved spoken-command tables, or the built-in ones if they can't be read
+pub(crate) fn spoken_commands<R: Runtime>(app: &AppHandle<R>) -> SpokenCommands {
+    match load_spoken(app) {
+        Ok(commands) => commands,
+        Err(e) => {
+            eprintln!("[Spoken] {}", e);
+            SpokenCommands::default()
+        }
+    }
+}
+
+/// Split segments into the ones to keep, with annotations removed, and the ones dropped.
+pub(crate) fn filter_segments(rules: &FilterRules, segments: Vec<TranscriptSegment>) -> (Vec<TranscriptSegment>, Vec<FilteredSegment>) {
+    let mut kept = Vec::with_capacity(segments.len());
+    let mut dropped = Vec::new();
+    for segment in segments {
+        let scores = Scores { avg_logprob: segment.avg_logprob, no_speech_prob: segment.no_speech_prob };
+        match rules.check(&segment.text, Some(scores)) {
+            Verdict::Keep(text) if text.is_empty() => {}
+            Verdict::Keep(text) => kept.push(TranscriptSegment { text, ..segment }),
+            Verdict::Drop(reason) => {
+                println!("[Filter] Dropped {:?}: {:?}", reason, segment.text);
+                dropped.push(FilteredSegment {
+                    start_ms: segment.start_ms,
+                    end_ms: segment.end_ms,
+                    text: segment.text,
+                    reason,
+                });
+            }
+        }
+    }
+    (kept, dropped)
+}
@@ -0,0 +1,205 @@
+// User find/replace rules applied to every transcript, in list order.
+
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplacementRule {
+    /// Assigned on first save
+    #[serde(default)]
+    pub id: String,
+    pub find: String,
+    /// For regex rules `$1`, `${name}` insert capture groups
+    pub replace: String,
+    /// Treat `find` as a regular expression instead of literal text
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Only match where `find` is not part of a longer word
+    #[serde(default)]
+    pub whole_word: bool,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[derive(Clone)]
+struct CompiledRule {
+    pattern: Regex,
+    replace: String,
+    expand: bool,
+}
+
+/// Enabled rules compiled once, ready to run over many transcripts
+#[derive(Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    /// Fails on the first rule whose pattern does not compile, naming it.
+    pub fn compile(rules: &[ReplacementRule]) -> Result<Self, String> {
+        let mut compiled = Vec::new();
+        for (i, rule) in rules.iter().enumerate() {
+            if !rule.enabled || rule.find.is_empty() {
+                continue;
+            }
+            let mut pattern = if rule.regex {
+                format!("(?:{})", rule.find)
+            } else {
+                regex::escape(&rule.find)
+            };
+            if rule.whole_word {
+                // A boundary next to punctuation would never match ("C++"), so only
+                // anchor the sides of a literal that end in a word character
+                let first = rule.find.chars().next().is_some_and(is_word_char);
+                let last = rule.find.chars().last().is_some_and(is_word_char);
+                if rule.regex || first {
+                    pattern = format!(r"\b{}", pattern);
+                }
+                if rule.regex || last {
+                    pattern = format!(r"{}\b", pattern);
+                }
+            }
+            let pattern = RegexBuilder::new(&pattern)
+                .case_insensitive(!rule.case_sensitive)
+                .build()
+                .map_err(|e| format!("Rule {} (\"{}\") is not a valid pattern: {}", i + 1, rule.find, e))?;
+            compiled.push(CompiledRule {
+                pattern,
+                replace: rule.replace.clone(),
+                expand: rule.regex,
+            });
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Run every rule in order, each on the output of the previous one.
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let replaced = if rule.expand {
+                rule.pattern.replace_all(&text, rule.replace.as_str())
+            } else {
+                rule.pattern.replace_all(&text, NoExpand(&rule.replace))
+            };
+            text = replaced.into_owned();
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(find: &str, replace: &str) -> ReplacementRule {
+        ReplacementRule {
+            id: String::new(),
+            find: find.to_string(),
+            replace: replace.to_string(),
+            regex: false,
+            case_sensitive: false,
+            whole_word: false,
+            enabled: true,
+        }
+    }
+
+    fn apply(rules: &[ReplacementRule], text: &str) -> String {
+        RuleSet::compile(rules).unwrap().apply(text)
+    }
+
+    #[test]
+    fn literal_rules_ignore_case_by_default() {
+        let rules = [rule("yappy", "Yappie")];
+        assert_eq!(apply(&rules, "I use Yappy and YAPPY daily"), "I use Yappie and Yappie daily");
+    }
+
+    #[test]
+    fn case_sensitive_rules_only_match_exact_case() {
+        let rules = [ReplacementRule { case_sensitive: true, ..rule("Go", "Golang") }];
+        assert_eq!(apply(&rules, "Go and go"), "Golang and go");
+    }
+
+    #[test]
+    fn literal_text_is_not_a_pattern() {
+        let rules = [rule("a.b", "x"), rule("cost", "$1")];
+        assert_eq!(apply(&rules, "a.b acb cost"), "x acb $1");
+    }
+
+    #[test]
+    fn whole_word_skips_parts_of_longer_words() {
+        let rules = [ReplacementRule { whole_word: true, ..rule("btw", "by the way") }];
+        assert_eq!(apply(&rules, "btw, abtwx btw."), "by the way, abtwx by the way.");
+    }
+
+    #[test]
+    fn whole_word_works_for_terms_ending_in_punctuation() {
+        let rules = [ReplacementRule { whole_word: true, ..rule("c++", "C++") }];
+        assert_eq!(apply(&rules, "I write c++ and abc++"), "I write C++ and abc++");
+    }
+
+    #[test]
+    fn whole_word_handles_non_ascii_letters() {
+        let rules = [ReplacementRule { whole_word: true, ..rule("café", "coffee shop") }];
+        assert_eq!(apply(&rules, "the café and cafés"), "the coffee shop and cafés");
+    }
+
+    #[test]
+    fn regex_rules_expand_capture_groups() {
+        let rules = [ReplacementRule { regex: true, ..rule(r"(\d+) percent", "$1%") }];
+        assert_eq!(apply(&rules, "up 20 percent"), "up 20%");
+    }
+
+    #[test]
+    fn regex_alternation_stays_inside_word_boundaries() {
+        let rules = [ReplacementRule { regex: true, whole_word: true, ..rule("asap|eod", "soon") }];
+        assert_eq!(apply(&rules, "asap or eod, not teodor"), "soon or soon, not teodor");
+    }
+
+    #[test]
+    fn rules_run_in_order_on_previous_output() {
+        let rules = [rule("brb", "be right back"), rule("right", "RIGHT")];
+        assert_eq!(apply(&rules, "brb"), "be RIGHT back");
+
+        let reversed = [rule("right", "RIGHT"), rule("brb", "be right back")];
+        assert_eq!(apply(&reversed, "brb"), "be right back");
+    }
+
+    #[test]
+    fn disabled_and_empty_rules_are_skipped() {
+        let rules = [ReplacementRule { enabled: false, ..rule("a", "b") }, rule("", "x")];
+        let set = RuleSet::compile(&rules).unwrap();
+        assert!(set.is_empty());
+        assert_eq!(set.apply("a"), "a");
+    }
+
+    #[test]
+    fn invalid_regex_names_the_rule() {
+        let rules = [rule("ok", "fine"), ReplacementRule { regex: true, ..rule("(unclosed", "") }];
+        let err = RuleSet::compile(&rules).err().unwrap();
+        assert!(err.contains("Rule 2"), "{}", err);
+    }
+
+    #[test]
+    fn json_fills_in_defaults() {
+        let rules: Vec<ReplacementRule> = serde_json::from_str(r#"[{"find": "yappy", "replace": "Yappie"}]"#).unwrap();
+        assert_eq!(rules, vec![rule("yappy", "Yappie")]);
+
+        let json = serde_json::to_string(&rules).unwrap();
+        let back: Vec<ReplacementRule> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, rules);
+    }
+}
@@ -0,0 +1,335 @@
+// Spoken dictation commands ("comma", "new paragraph", "scratch that") turned into
+// the punctuation and edits they stand for.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Punctuation that sticks to the previous word
+    Attach,
+    /// Like `Attach`, and the next word starts a sentence
+    EndSentence,
+    /// Opening quote or bracket, the next word sticks to it
+    Open,
+    /// Line or paragraph break
+    Break,
+    /// Drop the last sentence
+    Scratch,
+}
+
+use Action::*;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpokenCommand {
+    /// Words as Whisper spells them, lowercase
+    pub phrase: String,
+    pub action: Action,
+    /// Text the phrase turns into, unused by `Scratch`
+    #[serde(default)]
+    pub mark: String,
+}
+
+/// Phrase tables per language, editable by the user
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpokenCommands {
+    /// Phrases per Whisper language code
+    pub languages: BTreeMap<String, Vec<SpokenCommand>>,
+    /// Commands that are also everyday words ("the main point is"). They only count at an
+    /// utterance boundary, where Whisper heard a pause around them.
+    pub ordinary_words: Vec<String>,
+}
+
+/// Built-in phrases per language
+type Grammar = &'static [(&'static str, Action, &'static str)];
+
+const EN: Grammar = &[
+    ("period", EndSentence, "."),
+    ("full stop", EndSentence, "."),
+    ("question mark", EndSentence, "?"),
+    ("exclamation mark", EndSentence, "!"),
+    ("exclamation point", EndSentence, "!"),
+    ("comma", Attach, ","),
+    ("colon", Attach, ":"),
+    ("semicolon", Attach, ";"),
+    ("semi colon", Attach, ";"),
+    ("open quote", Open, "\""),
+    ("close quote", Attach, "\""),
+    ("end quote", Attach, "\""),
+    ("open paren", Open, "("),
+    ("open parenthesis", Open, "("),
+    ("close paren", Attach, ")"),
+    ("close parenthesis", Attach, ")"),
+    ("new line", Break, "\n"),
+    ("newline", Break, "\n"),
+    ("new paragraph", Break, "\n\n"),
+    ("scratch that", Scratch, ""),
+    ("delete that", Scratch, ""),
+];
+
+const DE: Grammar = &[
+    ("punkt", EndSentence, "."),
+    ("fragezeichen", EndSentence, "?"),
+    ("ausrufezeichen", EndSentence, "!"),
+    ("komma", Attach, ","),
+    ("doppelpunkt", Attach, ":"),
+    ("semikolon", Attach, ";"),
+    ("anführungszeichen auf", Open, "„"),
+    ("anführungszeichen zu", Attach, "“"),
+    ("klammer auf", Open, "("),
+    ("klammer zu", Attach, ")"),
+    ("neue zeile", Break, "\n"),
+    ("neuer absatz", Break, "\n\n"),
+    ("lösch das", Scratch, ""),
+    ("streich das", Scratch, ""),
+];
+
+const FR: Grammar = &[
+    ("point", EndSentence, "."),
+    ("point d'interrogation", EndSentence, "?"),
+    ("point d'exclamation", EndSentence, "!"),
+    ("virgule", Attach, ","),
+    ("deux points", Attach, ":"),
+    ("point-virgule", Attach, ";"),
+    ("point virgule", Attach, ";"),
+    ("ouvrez les guillemets", Open, "« "),
+    ("fermez les guillemets", Attach, " »"),
+    ("ouvrez la parenthèse", Open, "("),
+    ("fermez la parenthèse", Attach, ")"),
+    ("à la ligne", Break, "\n"),
+    ("nouvelle ligne", Break, "\n"),
+    ("nouveau paragraphe", Break, "\n\n"),
+    ("efface ça", Scratch, ""),
+    ("annule ça", Scratch, ""),
+];
+
+const ES: Grammar = &[
+    ("punto", EndSentence, "."),
+    ("punto final", EndSentence, "."),
+    ("signo de interrogación", EndSentence, "?"),
+    ("signo de exclamación", EndSentence, "!"),
+    ("coma", Attach, ","),
+    ("dos puntos", Attach, ":"),
+    ("punto y coma", Attach, ";"),
+    ("abrir comillas", Open, "\""),
+    ("cerrar comillas", Attach, "\""),
+    ("abrir paréntesis", Open, "("),
+    ("cerrar paréntesis", Attach, ")"),
+    ("nueva línea", Break, "\n"),
+    ("nueva linea", Break, "\n"),
+    ("nuevo párrafo", Break, "\n\n"),
+    ("nuevo parrafo", Break, "\n\n"),
+    ("borra eso", Scratch, ""),
+];
+
+const ORDINARY_WORDS: &[&str] = &["period", "colon", "punkt", "point", "punto", "coma"];
+
+impl Default for SpokenCommands {
+    fn default() -> Self {
+        let table = |grammar: Grammar| {
+            grammar
+                .iter()
+                .map(|&(phrase, action, mark)| SpokenCommand { phrase: phrase.to_string(), action, mark: mark.to_string() })
+                .collect()
+        };
+        let languages = [("en", EN), ("de", DE), ("fr", FR), ("es", ES)];
+        Self {
+            languages: languages.iter().map(|&(code, grammar)| (code.to_string(), table(grammar))).collect(),
+            ordinary_words: ORDINARY_WORDS.iter().map(|w| w.to_string()).collect(),
+        }
+    }
+}
+
+/// Lowercase and without the punctuation Whisper put around the word
+fn normalize(word: &str) -> String {
+    word.replace('’', "'")
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+        .to_lowercase()
+}
+
+/// Longest phrase starting at `words[at]`, and how many words it spans
+fn match_command<'a>(grammar: &'a [SpokenCommand], words: &[String], at: usize) -> Option<(&'a SpokenCommand, usize)> {
+    let mut best: Option<(&SpokenCommand, usize)> = None;
+    for command in grammar {
+        let parts: Vec<&str> = command.phrase.split_whitespace().collect();
+        let len = parts.len();
+        if len == 0 || at + len > words.len() || best.is_some_and(|(_, n)| n >= len) {
+            continue;
+        }
+        if parts.iter().zip(&words[at..]).all(|(p, w)| *p == w) {
+            best = Some((command, len));
+        }
+    }
+    best
+}
+
+/// `words[at]` ends the text, ends a sentence, or follows punctuation
+fn at_boundary(words: &[&str], at: usize) -> bool {
+    at + 1 == words.len()
+        || words[at].ends_with(['.', '?', '!'])
+        || at > 0 && words[at - 1].ends_with(['.', ',', '?', '!', ';', ':'])
+}
+
+fn ends_sentence(text: &str) -> bool {
+    text.is_empty() || text.ends_with(['.', '?', '!', '\n'])
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl SpokenCommands {
+    /// Interpret the commands in `text` with the phrase table of `language`.
+    /// Text in a language without a table comes back unchanged.
+    pub fn apply(&self, text: &str, language: &str) -> String {
+        let grammar = match self.languages.get(language) {
+            Some(grammar) => grammar,
+            None => return text.to_string(),
+        };
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let normalized: Vec<String> = words.iter().map(|w| normalize(w)).collect();
+
+        let mut out = String::new();
+        // The next word follows an open quote or a break, no space before it
+        let mut glued = false;
+        let mut capitalize_next = false;
+        let mut i = 0;
+        while i < words.len() {
+            let found = match_command(grammar, &normalized, i)
+                .filter(|&(_, len)| len > 1 || !self.ordinary_words.contains(&normalized[i]) || at_boundary(&words, i));
+            let (command, len) = match found {
+                Some(found) => found,
+                None => {
+                    if !out.is_empty() && !glued {
+                        out.push(' ');
+                    }
+                    if capitalize_next {
+                        out.push_str(&capitalize(words[i]));
+                    } else {
+                        out.push_str(words[i]);
+                    }
+                    glued = false;
+                    capitalize_next = false;
+                    i += 1;
+                    continue;
+                }
+            };
+            i += len;
+
+            let mark = command.mark.as_str();
+            match command.action {
+                Attach | EndSentence => {
+                    // The spoken mark replaces whatever Whisper guessed before it
+                    let kept = out.trim_end_matches([',', '.', ';', ':', '!', '?']).len();
+                    out.truncate(kept);
+                    out.push_str(mark);
+                    glued = false;
+                    capitalize_next = command.action == EndSentence;
+                }
+                Open => {
+                    if !out.is_empty() && !glued {
+                        out.push(' ');
+                    }
+                    out.push_str(mark);
+                    glued = true;
+                }
+                Break => {
+                    let kept = out.trim_end_matches(' ').len();
+                    out.truncate(kept);
+                    out.push_str(mark);
+                    glued = true;
+                    capitalize_next = true;
+                }
+                Scratch => {
+                    // Back to the end of the sentence before the last one
+                    let kept = out.trim_end_matches(['.', '?', '!', ',', ' ']).len();
+                    out.truncate(kept);
+                    let start = out.rfind(['.', '?', '!', '\n']).map(|p| p + 1).unwrap_or(0);
+                    out.truncate(start);
+                    glued = out.is_empty() || out.ends_with('\n');
+                    capitalize_next = ends_sentence(&out);
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(text: &str, language: &str) -> String {
+        SpokenCommands::default().apply(text, language)
+    }
+
+    #[test]
+    fn punctuation_words_become_marks() {
+        assert_eq!(apply("hello comma how are you question mark", "en"), "hello, how are you?");
+    }
+
+    #[test]
+    fn spoken_marks_replace_whisper_punctuation() {
+        assert_eq!(apply("Hello, comma, how are you? Question mark.", "en"), "Hello, how are you?");
+        assert_eq!(apply("Thanks. Exclamation point. see you", "en"), "Thanks! See you");
+    }
+
+    #[test]
+    fn breaks_start_a_capitalized_line() {
+        assert_eq!(apply("Dear Sam, new paragraph. thanks for the update.", "en"), "Dear Sam,\n\nThanks for the update.");
+        assert_eq!(apply("one new line two", "en"), "one\nTwo");
+    }
+
+    #[test]
+    fn quotes_hug_the_quoted_words() {
+        assert_eq!(apply("she said open quote hi close quote", "en"), "she said \"hi\"");
+    }
+
+    #[test]
+    fn scratch_that_drops_the_last_sentence() {
+        assert_eq!(apply("Keep this. Drop this, scratch that. Then more.", "en"), "Keep this. Then more.");
+        assert_eq!(apply("Nothing to keep. Scratch that.", "en"), "");
+    }
+
+    #[test]
+    fn longest_phrase_wins() {
+        assert_eq!(apply("ja, Punkt. nein", "de"), "ja. Nein");
+        assert_eq!(apply("un point virgule deux", "fr"), "un; deux");
+        assert_eq!(apply("uno punto y coma dos", "es"), "uno; dos");
+    }
+
+    #[test]
+    fn ordinary_words_only_count_at_a_boundary() {
+        let text = "The main point is a period of rest after the colon surgery.";
+        assert_eq!(apply(text, "en"), text);
+        assert_eq!(apply("Le point principal est clair", "fr"), "Le point principal est clair");
+        assert_eq!(apply("That is all. Period.", "en"), "That is all.");
+        assert_eq!(apply("see you period", "en"), "see you.");
+        assert_eq!(apply("Items, colon, eggs", "en"), "Items: eggs");
+    }
+
+    #[test]
+    fn unknown_languages_are_left_alone() {
+        assert_eq!(apply("ciao virgola", "it"), "ciao virgola");
+    }
+
+    #[test]
+    fn saved_tables_add_languages() {
+        let mut commands = SpokenCommands::default();
+        let virgola = SpokenCommand { phrase: "virgola".to_string(), action: Attach, mark: ",".to_string() };
+        commands.languages.insert("it".to_string(), vec![virgola]);
+        assert_eq!(commands.apply("ciao virgola come stai", "it"), "ciao, come stai");
+    }
+
+    #[test]
+    fn saved_ordinary_words_replace_the_built_in_ones() {
+        let commands = SpokenCommands { ordinary_words: Vec::new(), ..SpokenCommands::default() };
+        assert_eq!(commands.apply("le point principal", "fr"), "le. Principal");
+    }
+}
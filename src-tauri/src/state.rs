@@ -1,24 +1,265 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use crate::dsp::vad::EnergyVad;
+
+pub type WavFileWriter = hound::WavWriter<BufWriter<File>>;
+
+/// Live transcription worker of a take
+pub struct LiveSession {
+    pub handle: JoinHandle<Result<String, String>>,
+    /// Stops the worker between passes and skips its final pass over the take
+    pub cancelled: Arc<AtomicBool>,
+}
 
 // Wrapper to force Send on cpal::Stream (Mac-specific workaround)
 pub struct SendStream(#[allow(dead_code)] pub cpal::Stream);
 unsafe impl Send for SendStream {}
 
+/// System-audio capture: a cpal stream (WASAPI loopback, virtual loopback devices)
+/// or a `parec` child reading a PulseAudio/PipeWire monitor source
+pub enum LoopbackStream {
+    Cpal(#[allow(dead_code)] SendStream),
+    Parec(std::process::Child),
+}
+
+impl Drop for LoopbackStream {
+    fn drop(&mut self) {
+        if let LoopbackStream::Parec(child) = self {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// What a capture stream is reading from, before downmixing and resampling
+#[derive(Clone, serde::Serialize)]
+pub struct CaptureDevice {
+    pub name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// How mic and system audio end up in the take
+#[derive(Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoopbackMode {
+    /// One track with both summed
+    Mix,
+    /// Mic as "me", system audio as "them"
+    Separate,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CaptureSource {
+    Mic,
+    System,
+}
+
+/// Lines up mic and system audio arriving from two independent callbacks.
+/// Whatever both have delivered is released in equal lengths; when one side stalls
+/// (WASAPI loopback sends nothing while nothing plays) the other is padded with silence.
+pub struct DualCapture {
+    pub mode: LoopbackMode,
+    mic: VecDeque<f32>,
+    system: VecDeque<f32>,
+}
+
+/// How far one source may run ahead before the other is assumed silent (250ms)
+const MAX_SKEW: usize = 4000;
+
+impl DualCapture {
+    pub fn new(mode: LoopbackMode) -> Self {
+        Self { mode, mic: VecDeque::new(), system: VecDeque::new() }
+    }
+
+    /// Queue samples from one source and return the aligned (mic, system) audio now ready
+    pub fn push(&mut self, source: CaptureSource, data: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        match source {
+            CaptureSource::Mic => self.mic.extend(data.iter().copied()),
+            CaptureSource::System => self.system.extend(data.iter().copied()),
+        }
+        let mut ready = self.mic.len().min(self.system.len());
+        if self.mic.len().max(self.system.len()) > ready + MAX_SKEW {
+            ready = self.mic.len().max(self.system.len());
+        }
+        self.take(ready)
+    }
+
+    /// Everything still queued, the shorter side padded with silence
+    pub fn flush(&mut self) -> (Vec<f32>, Vec<f32>) {
+        self.take(self.mic.len().max(self.system.len()))
+    }
+
+    fn take(&mut self, n: usize) -> (Vec<f32>, Vec<f32>) {
+        let drain = |queue: &mut VecDeque<f32>| {
+            let mut out: Vec<f32> = queue.drain(..n.min(queue.len())).collect();
+            out.resize(n, 0.0);
+            out
+        };
+        (drain(&mut self.mic), drain(&mut self.system))
+    }
+}
+
+/// Ring of the most recent 16kHz samples heard while the mic is warm but not recording
+pub struct PreRoll {
+    pub samples: VecDeque<f32>,
+    pub capacity: usize,
+}
+
+impl PreRoll {
+    pub fn push(&mut self, data: &[f32]) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.samples.extend(data.iter().copied());
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+}
+
+/// Paused interval of a take, in wall-clock ms from the start of the take
+#[derive(Clone, serde::Serialize)]
+pub struct PauseSpan {
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Wall-clock bookkeeping for a take so durations exclude paused time
+#[derive(Default)]
+pub struct RecordingClock {
+    pub started: Option<Instant>,
+    pub paused_at: Option<Instant>,
+    pub pauses: Vec<PauseSpan>,
+}
+
+impl RecordingClock {
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+        self.paused_at = None;
+        self.pauses.clear();
+    }
+
+    pub fn pause(&mut self) {
+        self.pause_at(Instant::now());
+    }
+
+    pub fn resume(&mut self) {
+        self.resume_at(Instant::now());
+    }
+
+    /// Recorded time so far, not counting pauses (including one in progress)
+    pub fn active_ms(&self) -> u64 {
+        self.active_ms_at(Instant::now())
+    }
+
+    fn pause_at(&mut self, now: Instant) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    fn resume_at(&mut self, now: Instant) {
+        if let (Some(started), Some(paused_at)) = (self.started, self.paused_at.take()) {
+            self.pauses.push(PauseSpan {
+                start_ms: paused_at.duration_since(started).as_millis() as u64,
+                end_ms: now.duration_since(started).as_millis() as u64,
+            });
+        }
+    }
+
+    fn active_ms_at(&self, now: Instant) -> u64 {
+        let started = match self.started {
+            Some(started) => started,
+            None => return 0,
+        };
+        let wall = now.duration_since(started).as_millis() as u64;
+        let closed: u64 = self.pauses.iter().map(|p| p.end_ms - p.start_ms).sum();
+        let open = self.paused_at.map(|p| now.duration_since(p).as_millis() as u64).unwrap_or(0);
+        wall.saturating_sub(closed + open)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct WarmMic {
+    pub device_id: Option<String>,
+    pub preroll_ms: u32,
+}
+
+/// Every field is shared, so clones (e.g. the one owned by the capture callback) see the same state
+#[derive(Clone)]
 pub struct AudioState {
     pub stream: Arc<Mutex<Option<SendStream>>>,
+    /// Device behind `stream`, kept with it while the mic is warm
+    pub stream_device: Arc<Mutex<Option<CaptureDevice>>>,
     pub is_recording: Arc<Mutex<bool>>,
     pub max_amplitude: Arc<Mutex<f32>>,
     /// Rolling RMS level for the most recent audio chunk (0.0–1.0)
     pub current_level: Arc<Mutex<f32>>,
+    /// Captured audio for the current take, already 16kHz mono
+    pub samples: Arc<Mutex<Vec<f32>>>,
+    /// Optional archive copy of the take on disk
+    pub writer: Arc<Mutex<Option<WavFileWriter>>>,
+    pub archive_path: Arc<Mutex<Option<PathBuf>>>,
+    /// Whether the capture callback routes audio into the take (true) or the pre-roll ring
+    pub capturing: Arc<AtomicBool>,
+    /// Trailing-silence detector for auto-stop and its hang time in ms
+    pub vad: Arc<Mutex<Option<(EnergyVad, u64)>>>,
+    pub speech_started: Arc<AtomicBool>,
+    pub silence_reached: Arc<AtomicBool>,
+    pub preroll: Arc<Mutex<PreRoll>>,
+    /// Set while the input stream is kept open between takes
+    pub warm_mic: Arc<Mutex<Option<WarmMic>>>,
+    /// Samples are dropped while paused; the stream and archive writer stay open
+    pub is_paused: Arc<AtomicBool>,
+    pub clock: Arc<Mutex<RecordingClock>>,
+    /// Live transcription worker for the current take, joined for its final text
+    pub live: Arc<Mutex<Option<LiveSession>>>,
+    /// System-audio source of the current take, never kept warm
+    pub loopback_stream: Arc<Mutex<Option<LoopbackStream>>>,
+    /// Set when the take records mic and system audio together
+    pub dual: Arc<Mutex<Option<DualCapture>>>,
+    /// System audio of a take recorded with `LoopbackMode::Separate`; `samples` holds the mic
+    pub them: Arc<Mutex<Vec<f32>>>,
+    /// Sources of the current take: the main input, and system audio recorded alongside it
+    pub take_device: Arc<Mutex<Option<CaptureDevice>>>,
+    pub take_system_device: Arc<Mutex<Option<CaptureDevice>>>,
 }
 
 impl AudioState {
     pub fn new() -> Self {
         Self {
             stream: Arc::new(Mutex::new(None)),
+            stream_device: Arc::new(Mutex::new(None)),
             is_recording: Arc::new(Mutex::new(false)),
             max_amplitude: Arc::new(Mutex::new(0.0)),
             current_level: Arc::new(Mutex::new(0.0)),
+            samples: Arc::new(Mutex::new(Vec::new())),
+            writer: Arc::new(Mutex::new(None)),
+            archive_path: Arc::new(Mutex::new(None)),
+            capturing: Arc::new(AtomicBool::new(false)),
+            vad: Arc::new(Mutex::new(None)),
+            speech_started: Arc::new(AtomicBool::new(false)),
+            silence_reached: Arc::new(AtomicBool::new(false)),
+            preroll: Arc::new(Mutex::new(PreRoll {
+                samples: VecDeque::new(),
+                capacity: 0,
+            })),
+            warm_mic: Arc::new(Mutex::new(None)),
+            is_paused: Arc::new(AtomicBool::new(false)),
+            clock: Arc::new(Mutex::new(RecordingClock::default())),
+            live: Arc::new(Mutex::new(None)),
+            loopback_stream: Arc::new(Mutex::new(None)),
+            dual: Arc::new(Mutex::new(None)),
+            them: Arc::new(Mutex::new(Vec::new())),
+            take_device: Arc::new(Mutex::new(None)),
+            take_system_device: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -34,3 +275,99 @@ impl WhisperModelState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn clock(t0: Instant) -> RecordingClock {
+        RecordingClock { started: Some(t0), ..RecordingClock::default() }
+    }
+
+    #[test]
+    fn paused_time_is_not_recorded() {
+        let t0 = Instant::now();
+        let mut clock = clock(t0);
+        clock.pause_at(t0 + ms(1000));
+        assert_eq!(clock.active_ms_at(t0 + ms(1500)), 1000);
+        clock.resume_at(t0 + ms(3000));
+        assert_eq!(clock.active_ms_at(t0 + ms(4000)), 2000);
+        assert_eq!(clock.pauses.len(), 1);
+        assert_eq!((clock.pauses[0].start_ms, clock.pauses[0].end_ms), (1000, 3000));
+    }
+
+    #[test]
+    fn a_second_pause_keeps_the_first_start() {
+        let t0 = Instant::now();
+        let mut clock = clock(t0);
+        clock.pause_at(t0 + ms(1000));
+        clock.pause_at(t0 + ms(2000));
+        clock.resume_at(t0 + ms(3000));
+        assert_eq!((clock.pauses[0].start_ms, clock.pauses[0].end_ms), (1000, 3000));
+        // Resuming twice doesn't add an empty pause
+        clock.resume_at(t0 + ms(3500));
+        assert_eq!(clock.pauses.len(), 1);
+        assert_eq!(clock.active_ms_at(t0 + ms(5000)), 3000);
+    }
+
+    #[test]
+    fn separate_pauses_add_up() {
+        let t0 = Instant::now();
+        let mut clock = clock(t0);
+        clock.pause_at(t0 + ms(1000));
+        clock.resume_at(t0 + ms(2000));
+        clock.pause_at(t0 + ms(4000));
+        clock.resume_at(t0 + ms(4500));
+        assert_eq!(clock.pauses.len(), 2);
+        assert_eq!(clock.active_ms_at(t0 + ms(6000)), 4500);
+    }
+
+    #[test]
+    fn an_unstarted_clock_reads_zero() {
+        let mut clock = RecordingClock::default();
+        clock.pause();
+        clock.resume();
+        assert_eq!(clock.active_ms(), 0);
+        assert!(clock.pauses.is_empty());
+    }
+
+    #[test]
+    fn equal_feeds_come_out_aligned() {
+        let mut dual = DualCapture::new(LoopbackMode::Separate);
+        assert_eq!(dual.push(CaptureSource::Mic, &[0.1; 100]), (vec![], vec![]));
+        assert_eq!(dual.push(CaptureSource::System, &[0.2; 100]), (vec![0.1; 100], vec![0.2; 100]));
+        assert_eq!(dual.flush(), (vec![], vec![]));
+    }
+
+    #[test]
+    fn the_leading_side_waits_for_the_lagging_one() {
+        let mut dual = DualCapture::new(LoopbackMode::Mix);
+        dual.push(CaptureSource::Mic, &[0.1; 1000]);
+        let (mic, system) = dual.push(CaptureSource::System, &[0.2; 400]);
+        assert_eq!((mic.len(), system.len()), (400, 400));
+        let (mic, system) = dual.push(CaptureSource::System, &[0.2; 600]);
+        assert_eq!((mic, system), (vec![0.1; 600], vec![0.2; 600]));
+    }
+
+    #[test]
+    fn a_stalled_side_is_padded_past_the_skew_limit() {
+        let mut dual = DualCapture::new(LoopbackMode::Mix);
+        assert_eq!(dual.push(CaptureSource::Mic, &vec![0.1; MAX_SKEW]), (vec![], vec![]));
+        let (mic, system) = dual.push(CaptureSource::Mic, &[0.1]);
+        assert_eq!(mic, vec![0.1; MAX_SKEW + 1]);
+        assert_eq!(system, vec![0.0; MAX_SKEW + 1]);
+    }
+
+    #[test]
+    fn flush_pads_the_shorter_side() {
+        let mut dual = DualCapture::new(LoopbackMode::Separate);
+        dual.push(CaptureSource::System, &[0.2; 10]);
+        dual.push(CaptureSource::Mic, &[0.1; 4]);
+        assert_eq!(dual.flush(), (vec![0.0; 6], vec![0.2; 6]));
+    }
+}
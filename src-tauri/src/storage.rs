@@ -0,0 +1,40 @@
+// Small JSON files in the app data dir for data the backend owns.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Manager, Runtime};
+
+fn path<R: Runtime>(app: &AppHandle<R>, file: &str) -> Result<PathBuf, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(app_data_dir.join(file))
+}
+
+/// Contents of `file`, or the default if it was never saved
+pub(crate) fn load_json<R: Runtime, T: DeserializeOwned + Default>(app: &AppHandle<R>, file: &str) -> Result<T, String> {
+    let path = path(app, file)?;
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let data = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", file, e))
+}
+
+pub(crate) fn save_json<R: Runtime, T: Serialize + ?Sized>(app: &AppHandle<R>, file: &str, value: &T) -> Result<(), String> {
+    let path = path(app, file)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let data = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(&path, data).map_err(|e| format!("Failed to save {}: {}", file, e))
+}
+
+/// Unique id for a stored item, also when many are created in the same millisecond
+pub(crate) fn new_id(prefix: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("{}_{}_{}", prefix, millis, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
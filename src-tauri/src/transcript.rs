@@ -0,0 +1,172 @@
+// Structured transcription results shared by every transcription command.
+
+use serde::Serialize;
+use whisper_rs::{WhisperContext, WhisperState};
+use crate::commands::language::DetectedLanguage;
+use crate::postprocess::{filter::FilterReason, rules::RuleSet};
+
+#[derive(Clone, Serialize)]
+pub struct Transcript {
+    /// Cleaned-up text of the whole transcription
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+    /// What the hallucination filter removed, in time order
+    pub filtered: Vec<FilteredSegment>,
+    pub language: DetectedLanguage,
+    pub model: String,
+    /// "CPU", or the GPU backend the model ran on
+    pub backend: String,
+    /// Wall-clock time from the request to the result
+    pub processing_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    /// Mean log probability of the segment's text tokens
+    pub avg_logprob: f32,
+    /// Whisper's probability that the window held no speech, unset for segments from elsewhere
+    pub no_speech_prob: Option<f32>,
+    /// "Me" or "Them" for separate-channel takes
+    pub speaker: Option<String>,
+    /// Only filled when token timestamps are enabled
+    pub words: Vec<TranscriptWord>,
+}
+
+/// A segment dropped by the hallucination filter, with what Whisper had produced
+#[derive(Clone, Serialize)]
+pub struct FilteredSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    pub reason: FilterReason,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TranscriptWord {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    /// Mean probability of the word's tokens
+    pub probability: f32,
+}
+
+impl Transcript {
+    /// Rewrite the text and every segment with the user's replacement rules.
+    /// Word timings are left as Whisper heard them.
+    pub(crate) fn apply_rules(&mut self, rules: &RuleSet) {
+        if rules.is_empty() {
+            return;
+        }
+        self.text = rules.apply(&self.text);
+        for segment in &mut self.segments {
+            segment.text = rules.apply(&segment.text);
+        }
+    }
+}
+
+/// Segment texts as one line of text
+pub(crate) fn join_text(segments: &[TranscriptSegment]) -> String {
+    let mut text = String::new();
+    for segment in segments {
+        let segment_text = segment.text.trim();
+        if segment_text.is_empty() {
+            continue;
+        }
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(segment_text);
+    }
+    text
+}
+
+/// Whisper timestamps are in centiseconds
+fn to_ms(t: i64) -> u64 {
+    t.max(0) as u64 * 10
+}
+
+/// Segments of the last `full` run on `state`.
+/// `words` needs the run to have had token timestamps enabled.
+pub(crate) fn read_segments(
+    ctx: &WhisperContext,
+    state: &WhisperState,
+    words: bool,
+) -> Result<Vec<TranscriptSegment>, String> {
+    // Everything from end-of-text on is a special or timestamp token
+    let eot = ctx.token_eot();
+    let mut segments = Vec::with_capacity(state.full_n_segments().max(0) as usize);
+
+    for segment in state.as_iter() {
+        let text = segment.to_str_lossy().map_err(|e| format!("Failed to get segment text: {}", e))?.into_owned();
+
+        let mut logprob_sum = 0.0f32;
+        let mut text_tokens = 0u32;
+        let mut segment_words: Vec<TranscriptWord> = Vec::new();
+        // Tokens per word, to turn the summed probabilities into means
+        let mut word_tokens: Vec<u32> = Vec::new();
+
+        for j in 0..segment.n_tokens() {
+            let token = match segment.get_token(j) {
+                Some(token) => token,
+                None => return Err(format!("Failed to get token {} of segment {}", j, segment.segment_index())),
+            };
+            let data = token.token_data();
+            if data.id >= eot {
+                continue;
+            }
+            logprob_sum += data.plog;
+            text_tokens += 1;
+
+            if !words {
+                continue;
+            }
+            let token = token.to_str_lossy().map_err(|e| format!("Failed to get token text: {}", e))?.into_owned();
+            // A leading space starts a new word, anything else continues the current one
+            match (segment_words.last_mut(), word_tokens.last_mut()) {
+                (Some(word), Some(count)) if !token.starts_with(' ') => {
+                    word.text.push_str(&token);
+                    word.end_ms = to_ms(data.t1);
+                    word.probability += data.p;
+                    *count += 1;
+                }
+                _ => {
+                    segment_words.push(TranscriptWord {
+                        start_ms: to_ms(data.t0),
+                        end_ms: to_ms(data.t1),
+                        text: token,
+                        probability: data.p,
+                    });
+                    word_tokens.push(1);
+                }
+            }
+        }
+        for (word, count) in segment_words.iter_mut().zip(&word_tokens) {
+            word.probability /= *count as f32;
+            word.text = word.text.trim().to_string();
+        }
+        segment_words.retain(|w| !w.text.is_empty());
+
+        segments.push(TranscriptSegment {
+            start_ms: to_ms(segment.start_timestamp()),
+            end_ms: to_ms(segment.end_timestamp()),
+            text,
+            avg_logprob: if text_tokens > 0 { logprob_sum / text_tokens as f32 } else { 0.0 },
+            no_speech_prob: Some(segment.no_speech_probability()),
+            speaker: None,
+            words: segment_words,
+        });
+    }
+    Ok(segments)
+}
+
+/// Backend a model runs on for the given GPU setting
+pub(crate) fn backend_name(use_gpu: bool) -> String {
+    if use_gpu {
+        crate::commands::system::compiled_backend().to_string()
+    } else {
+        "CPU".to_string()
+    }
+}
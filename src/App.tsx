@@ -10,10 +10,11 @@ import { useConfigStore } from './stores/useConfigStore';
 import { useHistoryStore } from './stores/useHistoryStore';
 import { useShortcuts } from './hooks/useShortcuts';
 import { useCLIEvents } from './hooks/useCLIEvents';
-import { toggleRecord } from './lib/recordingController';
+import { toggleRecord, registerAutoStopListeners } from './lib/recordingController';
 import { listen } from '@tauri-apps/api/event';
 import { useDownloadStore } from './stores/useDownloadStore';
 import { useUpdater } from './hooks/useUpdater';
+import { useToastStore } from './stores/useToastStore';
 
 export type ViewType = 'main' | 'settings' | 'history';
 
@@ -110,6 +111,28 @@ const App: React.FC = () => {
     };
   }, []);
 
+  useEffect(() => registerAutoStopListeners(), []);
+
+  const isConfigReady = useConfigStore((state) => state.isInitialized);
+  const { warmMic, inputDeviceId, preRollMs } = useConfigStore((state) => state.config);
+  useEffect(() => {
+    if (!isConfigReady) return;
+    invoke('set_warm_mic', {
+      enabled: warmMic || false,
+      deviceId: inputDeviceId ?? null,
+      prerollMs: preRollMs ?? null,
+    }).catch(console.error);
+  }, [isConfigReady, warmMic, inputDeviceId, preRollMs]);
+
+  useEffect(() => {
+    const unlisten = listen<{requested: string, fallback: string}>('input-device-fallback', (event) => {
+      useToastStore.getState().show(`Microphone not found, using ${event.payload.fallback}`);
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
   return (
     <ViewContext.Provider value={{ view, setView }}>
       <Flex direction="column" height="100vh" style={{ overflow: 'hidden' }}>
@@ -46,12 +46,14 @@ export const OverlayApp: React.FC = () => {
   const [active, setActive] = useState(true); // starts true — shown only when recording
   const [duration, setDuration] = useState(0);
   const [level, setLevel] = useState(0);
+  const [partial, setPartial] = useState({ stable: '', tentative: '' });
   const startTimeRef = useRef(Date.now());
 
   useEffect(() => {
     const unStart = listen<number>('overlay-start', (e) => {
       startTimeRef.current = e.payload;
       setDuration(0);
+      setPartial({ stable: '', tentative: '' });
       setActive(true);
     });
 
@@ -65,10 +67,16 @@ export const OverlayApp: React.FC = () => {
       setLevel(e.payload);
     });
 
+    // Words so far from live transcription
+    const unPartial = listen<{ stable: string; tentative: string }>('partial-transcript', (e) => {
+      setPartial({ stable: e.payload.stable, tentative: e.payload.tentative });
+    });
+
     return () => {
       unStart.then(f => f());
       unStop.then(f => f());
       unLevel.then(f => f());
+      unPartial.then(f => f());
     };
   }, []);
 
@@ -136,6 +144,23 @@ export const OverlayApp: React.FC = () => {
         <div style={{ width: 1, height: 18, background: 'rgba(255,255,255,0.1)', flexShrink: 0 }} />
 
         <Waveform level={level} />
+
+        {(partial.stable || partial.tentative) && (
+          // rtl direction keeps the newest words visible and ellipsizes the start
+          <span style={{
+            maxWidth: '260px',
+            overflow: 'hidden',
+            textOverflow: 'ellipsis',
+            direction: 'rtl',
+            fontSize: '12px',
+          }}>
+            <bdi>
+              <span style={{ color: '#f4f4f5' }}>{partial.stable}</span>
+              {partial.stable && partial.tentative ? ' ' : ''}
+              <span style={{ color: 'rgba(244,244,245,0.5)' }}>{partial.tentative}</span>
+            </bdi>
+          </span>
+        )}
       </div>
 
       <style>{`
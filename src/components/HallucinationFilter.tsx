@@ -0,0 +1,127 @@
+import React, { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { useToastStore } from '../stores/useToastStore';
+import { Flex, Text, Button, Box, Switch, TextField, TextArea } from '@radix-ui/themes';
+
+interface FilterRules {
+  enabled: boolean;
+  strip_annotations: boolean;
+  annotation_words: string[];
+  phrases: string[];
+  phrase_keep_logprob: number;
+  no_speech_thold: number;
+  logprob_thold: number;
+}
+
+const parseLines = (text: string) => text.split('\n').map((l) => l.trim()).filter(Boolean);
+
+const NumberField: React.FC<{ label: string; value: number; step: number; onChange: (value: number) => void }> = ({ label, value, step, onChange }) => (
+  <Flex direction="column" gap="1" style={{ flex: 1 }}>
+    <Text size="1" style={{ color: '#a1a1aa' }}>{label}</Text>
+    <TextField.Root
+      size="1"
+      type="number"
+      step={step}
+      defaultValue={String(value)}
+      onBlur={(e) => {
+        const parsed = Number(e.target.value);
+        if (!Number.isNaN(parsed) && parsed !== value) onChange(parsed);
+      }}
+    />
+  </Flex>
+);
+
+export const HallucinationFilter: React.FC = () => {
+  const showToast = useToastStore(state => state.show);
+  const [rules, setRules] = useState<FilterRules | null>(null);
+  // Remount the uncontrolled fields after a reset
+  const [revision, setRevision] = useState(0);
+
+  useEffect(() => {
+    invoke<FilterRules>('get_hallucination_filter')
+      .then(setRules)
+      .catch(console.error);
+  }, []);
+
+  if (!rules) return null;
+
+  const save = async (changes: Partial<FilterRules>) => {
+    try {
+      setRules(await invoke<FilterRules>('save_hallucination_filter', { rules: { ...rules, ...changes } }));
+    } catch (e) {
+      showToast(`Could not save filter: ${e}`);
+    }
+  };
+
+  const reset = async () => {
+    try {
+      setRules(await invoke<FilterRules>('reset_hallucination_filter'));
+      setRevision((r) => r + 1);
+    } catch (e) {
+      showToast(`Could not reset filter: ${e}`);
+    }
+  };
+
+  return (
+    <Flex key={revision} direction="column" gap="2" py="2">
+      <Flex justify="between" align="center">
+        <Box>
+          <Text as="div" size="2" weight="medium" style={{ color: '#d4d4d8' }}>Hallucination filter</Text>
+          <Text as="div" size="1" style={{ color: '#71717a', marginTop: '2px' }}>
+            Drop text Whisper invents on silence or music
+          </Text>
+        </Box>
+        <Flex gap="2" align="center">
+          <Button size="1" variant="ghost" color="gray" onClick={reset}>Reset</Button>
+          <Switch size="1" checked={rules.enabled} onCheckedChange={(enabled) => save({ enabled })} />
+        </Flex>
+      </Flex>
+
+      {rules.enabled && (
+        <>
+          <Text as="label" size="1" style={{ color: '#a1a1aa' }}>
+            <Flex gap="2" align="center">
+              <Switch size="1" checked={rules.strip_annotations} onCheckedChange={(strip_annotations) => save({ strip_annotations })} />
+              Remove [Music], ♪ and notes mentioning:
+            </Flex>
+          </Text>
+          <TextField.Root
+            size="1"
+            disabled={!rules.strip_annotations}
+            defaultValue={rules.annotation_words.join(', ')}
+            onBlur={(e) => save({ annotation_words: e.target.value.split(',').map((w) => w.trim()).filter(Boolean) })}
+          />
+
+          <Text size="1" style={{ color: '#a1a1aa' }}>Phrases dropped when a segment is nothing else, one per line</Text>
+          <TextArea
+            size="1"
+            rows={4}
+            defaultValue={rules.phrases.join('\n')}
+            onBlur={(e) => save({ phrases: parseLines(e.target.value) })}
+          />
+
+          <Flex gap="2">
+            <NumberField
+              label="Keep phrase above log prob"
+              value={rules.phrase_keep_logprob}
+              step={0.1}
+              onChange={(phrase_keep_logprob) => save({ phrase_keep_logprob })}
+            />
+            <NumberField
+              label="Silence: no-speech above"
+              value={rules.no_speech_thold}
+              step={0.05}
+              onChange={(no_speech_thold) => save({ no_speech_thold })}
+            />
+            <NumberField
+              label="and log prob below"
+              value={rules.logprob_thold}
+              step={0.1}
+              onChange={(logprob_thold) => save({ logprob_thold })}
+            />
+          </Flex>
+        </>
+      )}
+    </Flex>
+  );
+};
@@ -57,11 +57,27 @@ export const HistoryItem: React.FC<Props> = ({ item }) => {
       <Flex direction="column" gap="2">
         <Flex justify="between" align="center">
           <Text size="2" color="gray">{timeAgo(item.timestamp)}</Text>
-          {item.backend && (
-            <Badge size="1" color="gray">
-              {item.backend}
-            </Badge>
-          )}
+          <Flex gap="1">
+            {item.transcript && (item.transcript.filtered?.length ?? 0) > 0 && (
+              <Badge
+                size="1"
+                color="gray"
+                title={item.transcript.filtered.map((s) => `${s.text.trim()} (${s.reason.replace('_', ' ')})`).join('\n')}
+              >
+                {item.transcript.filtered.length} filtered
+              </Badge>
+            )}
+            {item.language && (
+              <Badge size="1" color="gray">
+                {item.language.toUpperCase()}
+              </Badge>
+            )}
+            {item.backend && (
+              <Badge size="1" color="gray">
+                {item.backend}
+              </Badge>
+            )}
+          </Flex>
         </Flex>
 
         <Text
@@ -6,7 +6,7 @@ import { useConfigStore } from '../stores/useConfigStore';
 import { useRecordingStore } from '../stores/useRecordingStore';
 import { useToastStore } from '../stores/useToastStore';
 import { formatDuration } from '../lib/utils';
-import { toggleRecord } from '../lib/recordingController';
+import { toggleRecord, recordingController } from '../lib/recordingController';
 import { AudioVisualizer } from './AudioVisualizer';
 import { Flex, IconButton, Text, Box, Button } from '@radix-ui/themes';
 import { Mic, Square, History, Settings, Loader2 } from 'lucide-react';
@@ -16,6 +16,8 @@ export const MainView: React.FC = () => {
   const { isRecording, isTranscribing, startTime, cancelRecord } = useRecording();
   const config = useConfigStore((state) => state.config);
   
+  const isPaused = useRecordingStore((state) => state.isPaused);
+  const pausedMs = useRecordingStore((state) => state.pausedMs);
   const resultMessage = useRecordingStore((state) => state.resultMessage);
   const setResultMessage = useRecordingStore((state) => state.setResultMessage);
   const showToast = useToastStore((state) => state.show);
@@ -25,17 +27,17 @@ export const MainView: React.FC = () => {
 
   useEffect(() => {
     let interval: number;
-    if (isRecording && startTime) {
+    if (isRecording && startTime && !isPaused) {
       interval = window.setInterval(() => {
-        setDuration(Date.now() - startTime);
+        setDuration(Date.now() - startTime - pausedMs);
       }, 100);
-    } else {
+    } else if (!isRecording) {
       setDuration(0);
     }
     return () => {
       if (interval !== undefined) window.clearInterval(interval);
     };
-  }, [isRecording, startTime]);
+  }, [isRecording, startTime, isPaused, pausedMs]);
 
   useEffect(() => {
     if (prevTranscribingRef.current && !isTranscribing && !isRecording) {
@@ -163,10 +165,15 @@ export const MainView: React.FC = () => {
             <Text size="5" weight="bold" style={{ fontVariantNumeric: 'tabular-nums' }} aria-label={`Recording duration: ${formatDuration(duration)}`}>
               {formatDuration(duration)}
             </Text>
-            <AudioVisualizer active={true} />
-            <Button variant="ghost" color="gray" size="1" onClick={handleCancelClick} style={{ marginTop: '4px' }}>
-              Cancel
-            </Button>
+            <AudioVisualizer active={!isPaused} />
+            <Flex gap="3" style={{ marginTop: '4px' }}>
+              <Button variant="ghost" color="gray" size="1" onClick={() => recordingController.togglePause()}>
+                {isPaused ? 'Resume' : 'Pause'}
+              </Button>
+              <Button variant="ghost" color="gray" size="1" onClick={handleCancelClick}>
+                Cancel
+              </Button>
+            </Flex>
           </Flex>
         )}
 
@@ -0,0 +1,144 @@
+import React, { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { open, save as saveDialog } from '@tauri-apps/plugin-dialog';
+import { useToastStore } from '../stores/useToastStore';
+import { Flex, Text, Button, Box, IconButton, Switch, TextField, Checkbox } from '@radix-ui/themes';
+import { ArrowDown, ArrowUp, Download, Plus, Trash2, Upload } from 'lucide-react';
+
+interface ReplacementRule {
+  id: string;
+  find: string;
+  replace: string;
+  regex: boolean;
+  case_sensitive: boolean;
+  whole_word: boolean;
+  enabled: boolean;
+}
+
+const JSON_FILTER = [{ name: 'Rules', extensions: ['json'] }];
+
+const Flag: React.FC<{ label: string; checked: boolean; onChange: (checked: boolean) => void }> = ({ label, checked, onChange }) => (
+  <Text as="label" size="1" style={{ color: '#a1a1aa' }}>
+    <Flex gap="1" align="center">
+      <Checkbox size="1" checked={checked} onCheckedChange={(c) => onChange(c === true)} />
+      {label}
+    </Flex>
+  </Text>
+);
+
+export const ReplacementRules: React.FC = () => {
+  const showToast = useToastStore(state => state.show);
+  const [rules, setRules] = useState<ReplacementRule[]>([]);
+
+  useEffect(() => {
+    invoke<ReplacementRule[]>('list_replacement_rules')
+      .then(setRules)
+      .catch(console.error);
+  }, []);
+
+  // The backend rejects the whole list if any pattern is invalid, naming the rule
+  const persist = async (next: ReplacementRule[]) => {
+    try {
+      setRules(await invoke<ReplacementRule[]>('save_replacement_rules', { rules: next }));
+    } catch (e) {
+      showToast(`Could not save rules: ${e}`);
+    }
+  };
+
+  const update = (index: number, changes: Partial<ReplacementRule>) =>
+    persist(rules.map((rule, i) => (i === index ? { ...rule, ...changes } : rule)));
+
+  const move = (index: number, by: number) => {
+    const next = [...rules];
+    const [rule] = next.splice(index, 1);
+    next.splice(index + by, 0, rule);
+    persist(next);
+  };
+
+  const add = () =>
+    persist([...rules, { id: '', find: '', replace: '', regex: false, case_sensitive: false, whole_word: true, enabled: true }]);
+
+  const importRules = async () => {
+    const path = await open({ multiple: false, filters: JSON_FILTER });
+    if (typeof path !== 'string') return;
+    try {
+      // Imported rules go after the current ones, so existing ordering is kept
+      setRules(await invoke<ReplacementRule[]>('import_replacement_rules', { path, replace: false }));
+    } catch (e) {
+      showToast(`Could not import rules: ${e}`);
+    }
+  };
+
+  const exportRules = async () => {
+    const path = await saveDialog({ defaultPath: 'replacement-rules.json', filters: JSON_FILTER });
+    if (!path) return;
+    try {
+      await invoke('export_replacement_rules', { path });
+    } catch (e) {
+      showToast(`Could not export rules: ${e}`);
+    }
+  };
+
+  return (
+    <Flex direction="column" gap="2" py="2">
+      <Flex justify="between" align="center">
+        <Box>
+          <Text as="div" size="2" weight="medium" style={{ color: '#d4d4d8' }}>Replacements</Text>
+          <Text as="div" size="1" style={{ color: '#71717a', marginTop: '2px' }}>
+            Fix recurring mistakes after transcription, top to bottom
+          </Text>
+        </Box>
+        <Flex gap="1">
+          <IconButton size="1" variant="ghost" color="gray" title="Import" onClick={importRules}>
+            <Upload size={12} />
+          </IconButton>
+          <IconButton size="1" variant="ghost" color="gray" title="Export" onClick={exportRules} disabled={rules.length === 0}>
+            <Download size={12} />
+          </IconButton>
+          <Button size="1" variant="soft" onClick={add}>
+            <Plus size={12} /> Add rule
+          </Button>
+        </Flex>
+      </Flex>
+
+      {rules.map((rule, index) => (
+        <Flex key={rule.id} direction="column" gap="1" p="2" style={{ border: '1px solid #27272a', borderRadius: '8px' }}>
+          <Flex gap="2" align="center">
+            <TextField.Root
+              size="1"
+              style={{ flex: 1 }}
+              placeholder={rule.regex ? '(\\d+) percent' : 'find'}
+              defaultValue={rule.find}
+              onBlur={(e) => e.target.value !== rule.find && update(index, { find: e.target.value })}
+            />
+            <Text size="1" style={{ color: '#71717a' }}>→</Text>
+            <TextField.Root
+              size="1"
+              style={{ flex: 1 }}
+              placeholder={rule.regex ? '$1%' : 'replace with'}
+              defaultValue={rule.replace}
+              onBlur={(e) => e.target.value !== rule.replace && update(index, { replace: e.target.value })}
+            />
+            <Switch size="1" checked={rule.enabled} onCheckedChange={(enabled) => update(index, { enabled })} />
+          </Flex>
+          <Flex gap="3" align="center">
+            <Flag label="Regex" checked={rule.regex} onChange={(regex) => update(index, { regex })} />
+            <Flag label="Match case" checked={rule.case_sensitive} onChange={(case_sensitive) => update(index, { case_sensitive })} />
+            <Flag label="Whole word" checked={rule.whole_word} onChange={(whole_word) => update(index, { whole_word })} />
+            <Flex gap="2" ml="auto">
+              <IconButton size="1" variant="ghost" color="gray" disabled={index === 0} onClick={() => move(index, -1)}>
+                <ArrowUp size={12} />
+              </IconButton>
+              <IconButton size="1" variant="ghost" color="gray" disabled={index === rules.length - 1} onClick={() => move(index, 1)}>
+                <ArrowDown size={12} />
+              </IconButton>
+              <IconButton size="1" variant="ghost" color="gray" onClick={() => persist(rules.filter((_, i) => i !== index))}>
+                <Trash2 size={12} />
+              </IconButton>
+            </Flex>
+          </Flex>
+        </Flex>
+      ))}
+    </Flex>
+  );
+};
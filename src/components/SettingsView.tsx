@@ -5,9 +5,12 @@ import { useConfigStore } from '../stores/useConfigStore';
 import { useToastStore } from '../stores/useToastStore';
 import { ShortcutRecorder } from './ShortcutRecorder';
 import { ModelManager } from './ModelManager';
-import { DEFAULT_CONFIG } from '../types';
+import { VocabularyManager } from './VocabularyManager';
+import { ReplacementRules } from './ReplacementRules';
+import { HallucinationFilter } from './HallucinationFilter';
+import { AppConfig, DEFAULT_CONFIG } from '../types';
 import { useView } from '../App';
-import { Flex, Box, Text, Switch, SegmentedControl, IconButton, Button, Separator, ScrollArea, Code, AlertDialog } from '@radix-ui/themes';
+import { Flex, Box, Text, Switch, SegmentedControl, IconButton, Button, Separator, ScrollArea, Code, AlertDialog, Select, TextField } from '@radix-ui/themes';
 import { ChevronLeft, Info, RotateCcw } from 'lucide-react';
 
 // Reusable setting row: label on left, control on right
@@ -30,17 +33,39 @@ const SectionHeader: React.FC<{ label: string }> = ({ label }) => (
   </Text>
 );
 
+// Source languages offered in settings, as Whisper codes
+const LANGUAGES: [string, string][] = [
+  ['auto', 'Auto-detect'],
+  ['en', 'English'],
+  ['ne', 'Nepali'],
+  ['hi', 'Hindi'],
+  ['es', 'Spanish'],
+  ['fr', 'French'],
+  ['de', 'German'],
+  ['pt', 'Portuguese'],
+  ['it', 'Italian'],
+  ['ru', 'Russian'],
+  ['zh', 'Chinese'],
+  ['ja', 'Japanese'],
+  ['ko', 'Korean'],
+  ['ar', 'Arabic'],
+];
+
 export const SettingsView: React.FC = () => {
   const { setView } = useView();
   const { config, updateSetting } = useConfigStore();
   const showToast = useToastStore(state => state.show);
 
   const [sessionType, setSessionType] = useState<string>('x11');
+  const [inputDevices, setInputDevices] = useState<{ id: string; name: string; is_loopback: boolean }[]>([]);
 
   useEffect(() => {
     invoke<string>('get_session_type')
       .then(setSessionType)
       .catch(console.error);
+    invoke<{ id: string; name: string }[]>('list_input_devices')
+      .then(setInputDevices)
+      .catch(console.error);
   }, []);
 
   const isWayland = sessionType === 'wayland';
@@ -159,6 +184,92 @@ export const SettingsView: React.FC = () => {
             />
           </SettingRow>
 
+          <SettingRow label="Microphone" hint="Falls back to the system default if unplugged">
+            <Select.Root
+              size="1"
+              value={config.inputDeviceId ?? 'default'}
+              onValueChange={(value) => updateSetting('inputDeviceId', value === 'default' ? undefined : value)}
+            >
+              <Select.Trigger style={{ maxWidth: '180px' }} />
+              <Select.Content>
+                <Select.Item value="default">System default</Select.Item>
+                {inputDevices.map((d) => (
+                  <Select.Item key={d.id} value={d.id}>{d.name}</Select.Item>
+                ))}
+              </Select.Content>
+            </Select.Root>
+          </SettingRow>
+
+          <SettingRow label="System audio" hint="Also record what your speakers play, e.g. the other side of a call">
+            <Select.Root
+              size="1"
+              value={config.loopbackDeviceId ?? 'none'}
+              onValueChange={(value) => updateSetting('loopbackDeviceId', value === 'none' ? undefined : value)}
+            >
+              <Select.Trigger style={{ maxWidth: '180px' }} />
+              <Select.Content>
+                <Select.Item value="none">Off</Select.Item>
+                {inputDevices.filter((d) => d.is_loopback).map((d) => (
+                  <Select.Item key={d.id} value={d.id}>{d.name}</Select.Item>
+                ))}
+              </Select.Content>
+            </Select.Root>
+          </SettingRow>
+
+          {config.loopbackDeviceId && (
+            <SettingRow label="Separate speakers" hint='Transcribe mic and system audio as "Me" and "Them"'>
+              <Switch 
+                checked={config.loopbackMode === 'separate'} 
+                onCheckedChange={(c) => updateSetting('loopbackMode', c ? 'separate' : 'mix')} 
+              />
+            </SettingRow>
+          )}
+
+          <SettingRow label="Warm microphone" hint="Keeps the mic open so your first word is never clipped">
+            <Switch 
+              checked={config.warmMic || false} 
+              onCheckedChange={(c) => updateSetting('warmMic', c)} 
+            />
+          </SettingRow>
+
+          <SettingRow label="Stop on silence" hint="End dictation automatically when you stop talking">
+            <Switch 
+              checked={config.autoStopOnSilence || false} 
+              onCheckedChange={(c) => updateSetting('autoStopOnSilence', c)} 
+            />
+          </SettingRow>
+
+          <SettingRow label="Live transcription" hint="Show words in the overlay while you speak">
+            <Switch 
+              checked={config.liveTranscription || false} 
+              onCheckedChange={(c) => updateSetting('liveTranscription', c)} 
+            />
+          </SettingRow>
+
+          <SettingRow label="Maximum length" hint="Long takes also stop where the model or free memory would struggle">
+            <Select.Root
+              size="1"
+              value={String(config.maxRecordingMinutes ?? 0)}
+              onValueChange={(value) => updateSetting('maxRecordingMinutes', Number(value))}
+            >
+              <Select.Trigger style={{ maxWidth: '180px' }} />
+              <Select.Content>
+                <Select.Item value="0">Automatic</Select.Item>
+                <Select.Item value="5">5 minutes</Select.Item>
+                <Select.Item value="15">15 minutes</Select.Item>
+                <Select.Item value="30">30 minutes</Select.Item>
+                <Select.Item value="60">60 minutes</Select.Item>
+              </Select.Content>
+            </Select.Root>
+          </SettingRow>
+
+          <SettingRow label="Keep recordings" hint="Keep takes turned into subtitles, until their history entry is deleted">
+            <Switch 
+              checked={config.keepRecordings ?? true} 
+              onCheckedChange={(c) => updateSetting('keepRecordings', c)} 
+            />
+          </SettingRow>
+
           <SettingRow label="Sound effects">
             <Switch 
               checked={config.soundEnabled} 
@@ -187,6 +298,65 @@ export const SettingsView: React.FC = () => {
 
           <ModelManager />
 
+          <SettingRow label="Language" hint="Pick one if short recordings come out in the wrong language">
+            <Select.Root
+              size="1"
+              value={config.language || 'auto'}
+              onValueChange={(value) => updateSetting('language', value)}
+            >
+              <Select.Trigger style={{ maxWidth: '180px' }} />
+              <Select.Content>
+                {LANGUAGES.map(([code, name]) => (
+                  <Select.Item key={code} value={code}>{name}</Select.Item>
+                ))}
+              </Select.Content>
+            </Select.Root>
+          </SettingRow>
+
+          {(config.language || 'auto') === 'auto' && (
+            <SettingRow label="Detect only" hint="Comma-separated codes, e.g. en, ne, hi. Empty for any language">
+              <TextField.Root
+                size="1"
+                style={{ maxWidth: '180px' }}
+                placeholder="Any language"
+                defaultValue={(config.allowedLanguages ?? []).join(', ')}
+                onBlur={(e) => updateSetting(
+                  'allowedLanguages',
+                  e.target.value.split(',').map((c) => c.trim().toLowerCase()).filter(Boolean),
+                )}
+              />
+            </SettingRow>
+          )}
+
+          <SettingRow label="Accuracy" hint="Slower profiles retry unclear passages and search more alternatives">
+            <Select.Root
+              size="1"
+              value={config.decodingProfile || 'standard'}
+              onValueChange={(value) => updateSetting('decodingProfile', value as AppConfig['decodingProfile'])}
+            >
+              <Select.Trigger style={{ maxWidth: '180px' }} />
+              <Select.Content>
+                <Select.Item value="standard">Standard</Select.Item>
+                <Select.Item value="fast">Fast</Select.Item>
+                <Select.Item value="balanced">Balanced</Select.Item>
+                <Select.Item value="accurate">Accurate (beam search)</Select.Item>
+              </Select.Content>
+            </Select.Root>
+          </SettingRow>
+
+          <VocabularyManager />
+
+          <SettingRow label="Spoken commands" hint={'Say "comma", "new paragraph" or "scratch that" (English, German, French and Spanish built in)'}>
+            <Switch 
+              checked={config.spokenCommands || false} 
+              onCheckedChange={(c) => updateSetting('spokenCommands', c)} 
+            />
+          </SettingRow>
+
+          <ReplacementRules />
+
+          <HallucinationFilter />
+
           <SettingRow label="Translate to English" hint="Auto-translate non-English audio">
             <Switch 
               checked={config.localTranslate || false} 
@@ -194,6 +364,27 @@ export const SettingsView: React.FC = () => {
             />
           </SettingRow>
 
+          <SettingRow label="Remove rumble" hint="High-pass filter below 80 Hz">
+            <Switch 
+              checked={config.highPassFilter || false} 
+              onCheckedChange={(c) => updateSetting('highPassFilter', c)} 
+            />
+          </SettingRow>
+
+          <SettingRow label="Reduce noise" hint="Gate steady fan or office noise before transcribing">
+            <Switch 
+              checked={config.denoise || false} 
+              onCheckedChange={(c) => updateSetting('denoise', c)} 
+            />
+          </SettingRow>
+
+          <SettingRow label="Normalize volume" hint="Bring quiet recordings up to a common level">
+            <Switch 
+              checked={config.normalizeLoudness || false} 
+              onCheckedChange={(c) => updateSetting('normalizeLoudness', c)} 
+            />
+          </SettingRow>
+
           <Separator size="4" my="2" />
 
           {/* ─── Reset ─── */}
@@ -0,0 +1,137 @@
+import React, { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { useConfigStore } from '../stores/useConfigStore';
+import { useToastStore } from '../stores/useToastStore';
+import { Flex, Text, Button, Box, IconButton, Switch, TextField, TextArea } from '@radix-ui/themes';
+import { Plus, Trash2 } from 'lucide-react';
+
+interface VocabularyList {
+  id: string;
+  name: string;
+  terms: string[];
+  enabled: boolean;
+  profile: string | null; // null: used in every profile
+}
+
+interface VocabularyPrompt {
+  prompt: string;
+  tokens: number;
+  max_tokens: number;
+  dropped: string[];
+}
+
+// Terms are edited as free text, one per line or comma-separated
+const parseTerms = (text: string) =>
+  text.split(/[\n,]/).map((t) => t.trim()).filter(Boolean);
+
+export const VocabularyManager: React.FC = () => {
+  const { config, updateSetting } = useConfigStore();
+  const showToast = useToastStore(state => state.show);
+  const [lists, setLists] = useState<VocabularyList[]>([]);
+  const [budget, setBudget] = useState<VocabularyPrompt | null>(null);
+
+  useEffect(() => {
+    invoke<VocabularyList[]>('list_vocabularies')
+      .then(setLists)
+      .catch(console.error);
+  }, []);
+
+  // Token count needs the model's tokenizer; skip quietly if it isn't downloaded
+  useEffect(() => {
+    invoke<VocabularyPrompt | null>('get_vocabulary_prompt', {
+      model: config.localModel || 'Tiny',
+      useGpu: config.useLocalGPU || false,
+      profile: config.vocabularyProfile || null,
+    })
+      .then(setBudget)
+      .catch(() => setBudget(null));
+  }, [lists, config.localModel, config.useLocalGPU, config.vocabularyProfile]);
+
+  const save = async (list: VocabularyList) => {
+    try {
+      setLists(await invoke<VocabularyList[]>('save_vocabulary', { list }));
+    } catch (e) {
+      showToast(`Could not save vocabulary: ${e}`);
+    }
+  };
+
+  const remove = async (id: string) => {
+    try {
+      setLists(await invoke<VocabularyList[]>('delete_vocabulary', { id }));
+    } catch (e) {
+      showToast(`Could not delete vocabulary: ${e}`);
+    }
+  };
+
+  return (
+    <Flex direction="column" gap="2" py="2">
+      <Flex justify="between" align="center">
+        <Box>
+          <Text as="div" size="2" weight="medium" style={{ color: '#d4d4d8' }}>Vocabulary</Text>
+          <Text as="div" size="1" style={{ color: '#71717a', marginTop: '2px' }}>
+            Names and jargon Whisper should spell your way
+          </Text>
+        </Box>
+        <Button
+          size="1"
+          variant="soft"
+          onClick={() => save({ id: '', name: `List ${lists.length + 1}`, terms: [], enabled: true, profile: null })}
+        >
+          <Plus size={12} /> Add list
+        </Button>
+      </Flex>
+
+      <Flex gap="2" align="center">
+        <Text size="1" style={{ color: '#71717a' }}>Active profile</Text>
+        <TextField.Root
+          size="1"
+          style={{ flex: 1 }}
+          placeholder="None, shared lists only"
+          defaultValue={config.vocabularyProfile ?? ''}
+          onBlur={(e) => updateSetting('vocabularyProfile', e.target.value.trim())}
+        />
+      </Flex>
+
+      {lists.map((list) => (
+        <Flex key={list.id} direction="column" gap="1" p="2" style={{ border: '1px solid #27272a', borderRadius: '8px' }}>
+          <Flex gap="2" align="center">
+            <TextField.Root
+              size="1"
+              style={{ flex: 1 }}
+              defaultValue={list.name}
+              onBlur={(e) => e.target.value !== list.name && save({ ...list, name: e.target.value })}
+            />
+            <TextField.Root
+              size="1"
+              style={{ width: '110px' }}
+              placeholder="All profiles"
+              defaultValue={list.profile ?? ''}
+              onBlur={(e) => e.target.value.trim() !== (list.profile ?? '') && save({ ...list, profile: e.target.value.trim() || null })}
+            />
+            <Switch
+              size="1"
+              checked={list.enabled}
+              onCheckedChange={(enabled) => save({ ...list, enabled })}
+            />
+            <IconButton size="1" variant="ghost" color="gray" onClick={() => remove(list.id)}>
+              <Trash2 size={12} />
+            </IconButton>
+          </Flex>
+          <TextArea
+            size="1"
+            rows={2}
+            placeholder="Kubernetes, Priya Sharma, Yappie"
+            defaultValue={list.terms.join(', ')}
+            onBlur={(e) => save({ ...list, terms: parseTerms(e.target.value) })}
+          />
+        </Flex>
+      ))}
+
+      {budget && budget.dropped.length > 0 && (
+        <Text size="1" color="orange">
+          Vocabulary is {budget.tokens} tokens; Whisper only reads {budget.max_tokens}, so the last terms are left out: {budget.dropped.join(', ')}.
+        </Text>
+      )}
+    </Flex>
+  );
+};
@@ -3,6 +3,8 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { documentDir, join, dirname } from '@tauri-apps/api/path';
 import { recordingController } from '../lib/recordingController';
+import { languageOptions, preprocessOptions } from '../lib/config';
+import type { MediaInfo, SrtResult, Transcript } from '../types';
 import { useRecordingStore } from '../stores/useRecordingStore';
 import { useConfigStore } from '../stores/useConfigStore';
 import { useHistoryStore } from '../stores/useHistoryStore';
@@ -74,7 +76,9 @@ export const useRecording = () => {
 
   // transcribeFile function for file imports
   const transcribeFile = useCallback(
-    async (filePath: string) => {
+    // `stream` picks an audio stream from probe_media, the first one by default;
+    // `range` limits transcription to part of the file, timestamps stay those of the whole file
+    async (filePath: string, stream?: number, range?: { startMs?: number; endMs?: number }) => {
       if (store.isTranscribing || store.isRecording) {
         showToast('Busy recording or processing');
         return;
@@ -115,6 +119,19 @@ export const useRecording = () => {
         let text = '';
         let backendInfo = '';
 
+        // Fails early on files without audio or that nothing can decode
+        const media = await invoke<MediaInfo>('probe_media', { path: filePath });
+        console.log('[transcribeFile] Media:', media);
+        // Anything the app can't decode itself goes through the ffmpeg binary
+        const track = media.streams.find((s) => (stream === undefined ? s.kind === 'audio' : s.index === stream));
+        if (stream === undefined && media.decoder === 'ffmpeg') {
+          showToast(`${track?.codec ?? 'This format'} is decoded with ffmpeg, which must be installed`);
+        }
+        const fileDuration = media.duration_ms ?? 0;
+        const duration = range
+          ? Math.max(0, Math.min(range.endMs ?? fileDuration, fileDuration) - (range.startMs ?? 0))
+          : fileDuration;
+
         // Check if SRT output is enabled
         if (useSrt) {
           console.log('[transcribeFile] Using SRT generation mode');
@@ -124,13 +141,20 @@ export const useRecording = () => {
           // Build output path using ALL config options
           const outputPath = await buildSrtOutputPath(filePath, srtConfig);
 
-          const result = await invoke<string>('generate_srt', {
+          const { path: result, transcript } = await invoke<SrtResult>('generate_srt', {
             audioPath: filePath,
             model: config.localModel || 'Tiny',
             outputPath,
             translate: config.localTranslate || false,
             useGpu,
             duplicateMode: srtConfig.duplicateHandling || 'rename',
+            preprocess: preprocessOptions(config),
+            language: languageOptions(config),
+            decoding: config.decodingProfile ?? 'standard',
+            vocabularyProfile: config.vocabularyProfile || null,
+            stream,
+            startMs: range?.startMs,
+            endMs: range?.endMs,
           });
 
           console.log('[transcribeFile] SRT generated:', result);
@@ -141,12 +165,14 @@ export const useRecording = () => {
           await addItem({
             timestamp: id,
             text: result.split('/').pop() || 'transcription.srt',
-            duration: 0,
+            duration,
             error: false,
             backend: backendInfo,
             processingTime,
             isSrt: true,
             srtPath: result,
+            language: transcript.language.code,
+            transcript,
           });
 
           showToast('SRT file generated!');
@@ -155,22 +181,33 @@ export const useRecording = () => {
           const useGpu = config.useLocalGPU || false;
           backendInfo = useGpu ? 'Local (GPU)' : 'Local (CPU)';
 
-          text = await invoke<string>('transcribe_local', {
+          const transcript = await invoke<Transcript>('transcribe_local', {
             path: filePath,
             model: config.localModel || 'Tiny',
             useGpu,
             translate: config.localTranslate || false,
+            preprocess: preprocessOptions(config),
+            language: languageOptions(config),
+            decoding: config.decodingProfile ?? 'standard',
+            vocabularyProfile: config.vocabularyProfile || null,
+            spokenCommands: config.spokenCommands || false,
+            stream,
+            startMs: range?.startMs,
+            endMs: range?.endMs,
           });
+          text = transcript.text;
 
           const processingTime = Date.now() - tStart;
 
           await addItem({
             timestamp: id,
             text: text || '',
-            duration: 0,
+            duration,
             error: false,
             backend: backendInfo,
             processingTime,
+            language: transcript.language.code,
+            transcript,
           });
         }
       } catch (err: unknown) {
@@ -6,6 +6,8 @@ import { useHistoryStore } from '../stores/useHistoryStore';
 import { useConfigStore } from '../stores/useConfigStore';
 import { useToastStore } from '../stores/useToastStore';
 import { useSrtConfigStore } from '../stores/useSrtConfigStore';
+import { languageOptions, preprocessOptions } from '../lib/config';
+import type { SrtResult } from '../types';
 import { documentDir, join } from '@tauri-apps/api/path';
 
 interface SrtProgressPayload {
@@ -71,13 +73,17 @@ export const useSrtGeneration = () => {
           outputPath = await join(outputDir, `${filename}.srt`);
         }
 
-        const result = await invoke<string>('generate_srt', {
+        const { path: result, transcript } = await invoke<SrtResult>('generate_srt', {
           audioPath,
           model: config.localModel || 'Tiny',
           outputPath,
           translate: config.localTranslate || false,
           useGpu: config.useLocalGPU || false,
           duplicateMode: duplicateHandling || 'rename',
+          preprocess: preprocessOptions(config),
+          language: languageOptions(config),
+          decoding: config.decodingProfile ?? 'standard',
+          vocabularyProfile: config.vocabularyProfile || null,
         });
 
         // Add to history
@@ -90,6 +96,8 @@ export const useSrtGeneration = () => {
           processingTime: 0,
           isSrt: true,
           srtPath: result,
+          language: transcript.language.code,
+          transcript,
         });
 
         showToast('SRT file generated!');
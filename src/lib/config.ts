@@ -26,6 +26,24 @@ export async function getConfig(): Promise<AppConfig> {
   const useLocalGPU = await store.get<boolean>('useLocalGPU');
   const globalShortcut = await store.get<string>('globalShortcut');
   const localTranslate = await store.get<boolean>('localTranslate');
+  const inputDeviceId = await store.get<string>('inputDeviceId');
+  const autoStopOnSilence = await store.get<boolean>('autoStopOnSilence');
+  const autoStopHangMs = await store.get<number>('autoStopHangMs');
+  const warmMic = await store.get<boolean>('warmMic');
+  const preRollMs = await store.get<number>('preRollMs');
+  const maxRecordingMinutes = await store.get<number>('maxRecordingMinutes');
+  const liveTranscription = await store.get<boolean>('liveTranscription');
+  const highPassFilter = await store.get<boolean>('highPassFilter');
+  const denoise = await store.get<boolean>('denoise');
+  const normalizeLoudness = await store.get<boolean>('normalizeLoudness');
+  const loopbackDeviceId = await store.get<string>('loopbackDeviceId');
+  const loopbackMode = await store.get<'mix' | 'separate'>('loopbackMode');
+  const language = await store.get<string>('language');
+  const allowedLanguages = await store.get<string[]>('allowedLanguages');
+  const decodingProfile = await store.get<'standard' | 'fast' | 'balanced' | 'accurate'>('decodingProfile');
+  const spokenCommands = await store.get<boolean>('spokenCommands');
+  const keepRecordings = await store.get<boolean>('keepRecordings');
+  const vocabularyProfile = await store.get<string>('vocabularyProfile');
 
   // Check Autostart status dynamically
   let autostart = false;
@@ -48,11 +66,46 @@ export async function getConfig(): Promise<AppConfig> {
     useLocalGPU: useLocalGPU ?? DEFAULT_CONFIG.useLocalGPU,
     globalShortcut: globalShortcut ?? DEFAULT_CONFIG.globalShortcut,
     localTranslate: localTranslate ?? DEFAULT_CONFIG.localTranslate,
+    inputDeviceId: inputDeviceId ?? undefined,
+    autoStopOnSilence: autoStopOnSilence ?? DEFAULT_CONFIG.autoStopOnSilence,
+    autoStopHangMs: autoStopHangMs ?? DEFAULT_CONFIG.autoStopHangMs,
+    warmMic: warmMic ?? DEFAULT_CONFIG.warmMic,
+    preRollMs: preRollMs ?? DEFAULT_CONFIG.preRollMs,
+    maxRecordingMinutes: maxRecordingMinutes ?? DEFAULT_CONFIG.maxRecordingMinutes,
+    liveTranscription: liveTranscription ?? DEFAULT_CONFIG.liveTranscription,
+    highPassFilter: highPassFilter ?? DEFAULT_CONFIG.highPassFilter,
+    denoise: denoise ?? DEFAULT_CONFIG.denoise,
+    normalizeLoudness: normalizeLoudness ?? DEFAULT_CONFIG.normalizeLoudness,
+    loopbackDeviceId: loopbackDeviceId ?? undefined,
+    loopbackMode: loopbackMode ?? DEFAULT_CONFIG.loopbackMode,
+    language: language ?? DEFAULT_CONFIG.language,
+    allowedLanguages: allowedLanguages ?? DEFAULT_CONFIG.allowedLanguages,
+    decodingProfile: decodingProfile ?? DEFAULT_CONFIG.decodingProfile,
+    spokenCommands: spokenCommands ?? DEFAULT_CONFIG.spokenCommands,
+    keepRecordings: keepRecordings ?? DEFAULT_CONFIG.keepRecordings,
+    vocabularyProfile: vocabularyProfile ?? DEFAULT_CONFIG.vocabularyProfile,
   };
 
   return cachedConfig;
 }
 
+/** Preprocessing stages in the shape the transcription commands expect */
+export function preprocessOptions(config: AppConfig) {
+  return {
+    high_pass: config.highPassFilter || false,
+    denoise: config.denoise || false,
+    normalize: config.normalizeLoudness || false,
+  };
+}
+
+/** Source language in the shape the transcription commands expect */
+export function languageOptions(config: AppConfig) {
+  return {
+    language: config.language && config.language !== 'auto' ? config.language : null,
+    allowed: config.allowedLanguages ?? [],
+  };
+}
+
 export async function updateConfig(key: keyof AppConfig, value: any) {
   if (!store) return;
   if (key === 'autostart') return;
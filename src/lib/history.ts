@@ -1,9 +1,19 @@
+import { invoke } from "@tauri-apps/api/core";
 import { getStore } from "./config";
 import { HistoryItem } from "../types";
 
 const MAX_HISTORY = 50;
 type Listener = () => void;
 
+// Kept takes live as long as their history entry
+const discardRecordings = (items: HistoryItem[]) => {
+    for (const item of items) {
+        if (item.recordingPath) {
+            invoke('discard_recording', { path: item.recordingPath }).catch(console.error);
+        }
+    }
+};
+
 export class HistoryManager {
     private listeners: Listener[] = [];
 
@@ -33,7 +43,7 @@ export class HistoryManager {
         list = list.filter(h => h.timestamp !== item.timestamp);
         
         list.unshift(item);
-        if (list.length > MAX_HISTORY) list.pop();
+        if (list.length > MAX_HISTORY) discardRecordings(list.splice(MAX_HISTORY));
         
         await store.set('history', list);
         await store.save();
@@ -45,6 +55,7 @@ export class HistoryManager {
         if (!store) return;
 
         let list = await this.getHistory();
+        discardRecordings(list.filter(h => h.timestamp === timestamp));
         list = list.filter(h => h.timestamp !== timestamp);
         
         await store.set('history', list);
@@ -55,6 +66,7 @@ export class HistoryManager {
     async clear() {
         const store = getStore();
         if (!store) return;
+        discardRecordings(await this.getHistory());
         await store.set('history', []);
         await store.save();
         this.notify();
@@ -1,19 +1,26 @@
 // Module-level recording controller - stable references for shortcuts
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { writeText } from '@tauri-apps/plugin-clipboard-manager';
 import { documentDir, join } from '@tauri-apps/api/path';
 import { audioController } from './audio';
+import { languageOptions, preprocessOptions } from './config';
 import { useRecordingStore } from '../stores/useRecordingStore';
 import { useHistoryStore } from '../stores/useHistoryStore';
 import { useToastStore } from '../stores/useToastStore';
 import { useConfigStore } from '../stores/useConfigStore';
 import { useSrtConfigStore } from '../stores/useSrtConfigStore';
+import type { RecordingResult, SrtResult, Transcript } from '../types';
 
 // State
 let _isRecording = false;
 let _isTranscribing = false;
 let _currentTranscriptionId = 0;
 let _startTime = 0;
+let _isPaused = false;
+let _pausedAt = 0;
+let _pausedTotal = 0; // ms spent paused in the current take
+let _isLive = false; // take is transcribed while recording
 let _recordTimer: ReturnType<typeof setInterval> | null = null;
 
 // Helper to get fresh state from stores
@@ -28,9 +35,58 @@ const updateReactState = () => {
     isRecording: _isRecording,
     isTranscribing: _isTranscribing,
     startTime: _startTime,
+    isPaused: _isPaused,
+    pausedMs: _pausedTotal,
   });
 };
 
+// Copy / paste / notify for a finished dictation
+const deliverText = async (text: string) => {
+  const config = getConfig();
+  const showToast = getShowToast();
+
+  if (!text || !config.autoCopy) return;
+  await writeText(text);
+
+  if (config.autoPaste) {
+    try {
+      // Wait for paste attempt
+      await new Promise(resolve => setTimeout(resolve, 100));
+      await invoke('paste_text');
+      showToast('Pasted');
+
+      if (config.notificationEnabled) {
+        await invoke('send_notification', {
+          title: 'Yappie',
+          body: 'Transcription pasted!',
+        });
+      }
+    } catch (e: any) {
+      // Paste failed (Wayland security restriction)
+      showToast('Copied! Press Ctrl+V to paste');
+      console.warn('Auto-paste failed:', e);
+
+      if (config.notificationEnabled) {
+        try {
+          await invoke('send_notification', {
+            title: 'Yappie',
+            body: 'Copied to clipboard. Press Ctrl+V to paste.',
+          });
+        } catch (_) {}
+      }
+    }
+  } else if (config.notificationEnabled) {
+    try {
+      await invoke('send_notification', {
+        title: 'Yappie',
+        body: 'Transcription copied to clipboard. Press Ctrl+V to paste.',
+      });
+    } catch (e) {
+      console.error('Notification error:', e);
+    }
+  }
+};
+
 export const recordingController = {
   // Getters
   get isRecording() { return _isRecording; },
@@ -64,9 +120,55 @@ export const recordingController = {
     
     try {
       console.log('[RecordingController] Starting recording...');
-      await invoke('start_recording');
+      // SRT generation works from a file, so only archive the take to disk in that mode
+      const srtEnabled = getSrtConfig().enabled;
+      // Backend stops and transcribes on its own after trailing silence (plain dictation only)
+      const autoStop = config.autoStopOnSilence && !srtEnabled
+        ? {
+            hang_ms: config.autoStopHangMs ?? 1500,
+            threshold: null,
+            model,
+            use_gpu: config.useLocalGPU || false,
+            translate: config.localTranslate || false,
+            preprocess: preprocessOptions(config),
+            language: languageOptions(config),
+            decoding: config.decodingProfile ?? 'standard',
+            vocabulary_profile: config.vocabularyProfile || null,
+            spoken_commands: config.spokenCommands || false,
+          }
+        : null;
+      // Stop before the take outgrows what the model or free memory can handle
+      const limit = await invoke<{ max_secs: number; limited_by: string }>('get_recording_limit', { model });
+      const userMaxSecs = (config.maxRecordingMinutes ?? 0) * 60;
+      const maxDurationSecs = userMaxSecs > 0 ? Math.min(userMaxSecs, limit.max_secs) : limit.max_secs;
+      // Live mode transcribes while recording; SRT still needs the full file pass
+      const live = config.liveTranscription && !srtEnabled
+        ? {
+            model,
+            use_gpu: config.useLocalGPU || false,
+            translate: config.localTranslate || false,
+            preprocess: preprocessOptions(config),
+            spoken_commands: config.spokenCommands || false,
+            decoding: config.decodingProfile ?? 'standard',
+            vocabulary_profile: config.vocabularyProfile || null,
+            language: languageOptions(config),
+          }
+        : null;
+      await invoke('start_recording', {
+        deviceId: config.inputDeviceId ?? null,
+        archive: srtEnabled,
+        autoStop,
+        maxDurationSecs,
+        live,
+        loopback: config.loopbackDeviceId
+          ? { device_id: config.loopbackDeviceId, mode: config.loopbackMode ?? 'mix' }
+          : null,
+      });
+      _isLive = live !== null;
       _isRecording = true;
       _startTime = Date.now();
+      _isPaused = false;
+      _pausedTotal = 0;
       if (config.soundEnabled) audioController.playStart();
       
       if (_recordTimer) clearInterval(_recordTimer);
@@ -94,6 +196,10 @@ export const recordingController = {
     console.log('[RecordingController] SRT enabled:', srtConfig.enabled);
     
     _isRecording = false;
+    if (_isPaused) {
+      _pausedTotal += Date.now() - _pausedAt;
+      _isPaused = false;
+    }
     const currentId = _startTime; // capture ID for this transcription
     _currentTranscriptionId = currentId;
     if (config.soundEnabled) audioController.playEnd();
@@ -108,12 +214,13 @@ export const recordingController = {
     
     updateReactState();
     
-    const duration = Date.now() - _startTime;
-    
     try {
-      const path = await invoke<string>('stop_recording');
+      const recording = await invoke<RecordingResult>('stop_recording');
+      const { path, waveform } = recording;
+      const duration = recording.duration_ms;
       
       if (duration < 500) {
+        if (path) invoke('discard_recording', { path }).catch(console.error);
         showToast('Too short, discarded');
         _isTranscribing = false;
         useRecordingStore.setState({ isGeneratingSrt: false });
@@ -141,6 +248,10 @@ export const recordingController = {
           const useGpu = config.useLocalGPU || false;
           backendInfo = useGpu ? 'SRT (GPU)' : 'SRT (CPU)';
           
+          if (!path) {
+            throw new Error('Recording was not saved to disk');
+          }
+
           // Build output path - for recordings, we always use default dir (no source file path)
           const docsDir = await documentDir();
           const outputDir = await join(docsDir, 'YappieOutputs');
@@ -156,28 +267,37 @@ export const recordingController = {
           const outputPath = await join(outputDir, `${filename}.srt`);
           console.log('[RecordingController] SRT output path:', outputPath);
           
-          const result = await invoke<string>('generate_srt', {
+          const result = await invoke<SrtResult>('generate_srt', {
             audioPath: path,
             model: config.localModel || 'Tiny',
             outputPath,
             translate: config.localTranslate || false,
             useGpu,
             duplicateMode: srtConfig.duplicateHandling || 'rename',
+            preprocess: preprocessOptions(config),
+            language: languageOptions(config),
+            decoding: config.decodingProfile ?? 'standard',
+            vocabularyProfile: config.vocabularyProfile || null,
+            keepArchive: config.keepRecordings ?? true,
           });
           
-          text = `SRT saved: ${result.split('/').pop()}`;
+          text = `SRT saved: ${result.path.split('/').pop()}`;
           
           // Add to history as SRT
           const processingTime = Date.now() - tStart;
           addItem({
             timestamp: id,
-            text: result.split('/').pop() || 'transcription.srt',
+            text: result.path.split('/').pop() || 'transcription.srt',
             duration,
             error: false,
             backend: backendInfo,
             processingTime,
             isSrt: true,
-            srtPath: result,
+            srtPath: result.path,
+            recordingPath: result.archive_path ?? undefined,
+            waveform,
+            language: result.transcript.language.code,
+            transcript: result.transcript,
           });
           
           showToast('SRT file generated!');
@@ -187,12 +307,23 @@ export const recordingController = {
           const useGpu = config.useLocalGPU || false;
           backendInfo = useGpu ? 'Local (GPU)' : 'Local (CPU)';
           
-          text = await invoke<string>('transcribe_local', {
-            path,
-            model: config.localModel || 'Tiny',
-            useGpu,
-            translate: config.localTranslate || false,
-          });
+          // Live sessions only return text
+          let transcript: Transcript | undefined;
+          if (_isLive) {
+            text = await invoke<string>('finish_live_transcription');
+          } else {
+            transcript = await invoke<Transcript>('transcribe_recording', {
+              model: config.localModel || 'Tiny',
+              useGpu,
+              translate: config.localTranslate || false,
+              preprocess: preprocessOptions(config),
+              language: languageOptions(config),
+              decoding: config.decodingProfile ?? 'standard',
+              vocabularyProfile: config.vocabularyProfile || null,
+              spokenCommands: config.spokenCommands || false,
+            });
+            text = transcript.text;
+          }
           
           const processingTime = Date.now() - tStart;
           
@@ -207,51 +338,19 @@ export const recordingController = {
             error: false,
             backend: backendInfo,
             processingTime,
+            waveform,
+            language: transcript?.language.code,
+            transcript,
           });
           
-          if (text && config.autoCopy) {
-            await writeText(text);
-            
-            if (config.autoPaste) {
-              try {
-                // Wait for paste attempt
-                await new Promise(resolve => setTimeout(resolve, 100));
-                await invoke('paste_text');
-                showToast('Pasted');
-                
-                if (config.notificationEnabled) {
-                  await invoke('send_notification', {
-                    title: 'Yappie',
-                    body: 'Transcription pasted!',
-                  });
-                }
-              } catch (e: any) {
-                // Paste failed (Wayland security restriction)
-                showToast('Copied! Press Ctrl+V to paste');
-                console.warn('Auto-paste failed:', e);
-                
-                if (config.notificationEnabled) {
-                  try {
-                    await invoke('send_notification', {
-                      title: 'Yappie',
-                      body: 'Copied to clipboard. Press Ctrl+V to paste.',
-                    });
-                  } catch (_) {}
-                }
-              }
-            } else if (config.notificationEnabled) {
-              try {
-                await invoke('send_notification', {
-                  title: 'Yappie',
-                  body: 'Transcription copied to clipboard. Press Ctrl+V to paste.',
-                });
-              } catch (e) {
-                console.error('Notification error:', e);
-              }
-            }
-          }
+          await deliverText(text);
         }
       } catch (err: unknown) {
+        // Cancelled takes stop with an error from the backend; nothing to record or keep
+        if (_currentTranscriptionId !== currentId) {
+          if (path) invoke('discard_recording', { path }).catch(console.error);
+          return;
+        }
         console.error('[RecordingController] Transcribe Error', err);
         addItem({
           timestamp: id,
@@ -276,6 +375,24 @@ export const recordingController = {
     }
   },
 
+  async togglePause() {
+    if (!_isRecording) return;
+    try {
+      if (_isPaused) {
+        await invoke('resume_recording');
+        _pausedTotal += Date.now() - _pausedAt;
+        _isPaused = false;
+      } else {
+        await invoke('pause_recording');
+        _pausedAt = Date.now();
+        _isPaused = true;
+      }
+      updateReactState();
+    } catch (e) {
+      console.error('[RecordingController] Pause Error', e);
+    }
+  },
+
   async toggleRecord() {
     console.log('[RecordingController] Toggle, isRecording:', _isRecording);
     if (_isRecording) {
@@ -306,12 +423,109 @@ export const recordingController = {
     
     if (_isRecording && config.soundEnabled) audioController.playEnd();
     _isRecording = false;
+    _isPaused = false;
     if (_recordTimer) clearInterval(_recordTimer);
     updateReactState();
-    invoke('stop_recording').catch(console.error);
+    invoke<RecordingResult>('stop_recording')
+      .then(({ path }) => path && invoke('discard_recording', { path }))
+      .catch(console.error);
   },
 };
 
+interface AutoTranscriptionPayload {
+  text: string | null;
+  transcript: Transcript | null; // null for live sessions
+  error: string | null;
+}
+
+// Backend-driven stop (trailing silence): mirror stopRecord's state changes and deliver the result
+export const registerAutoStopListeners = () => {
+  let autoStopId = 0;
+  let autoStopDuration = 0;
+  let autoStopWaveform: number[] | undefined;
+
+  const unStopped = listen<{ reason: string; path: string | null; recording: RecordingResult | null }>('recording-auto-stopped', (event) => {
+    if (!_isRecording) return;
+    const config = getConfig();
+    _isRecording = false;
+    if (_isPaused) {
+      _pausedTotal += Date.now() - _pausedAt;
+      _isPaused = false;
+    }
+    autoStopId = _startTime;
+    autoStopDuration = event.payload.recording?.duration_ms ?? Date.now() - _startTime - _pausedTotal;
+    autoStopWaveform = event.payload.recording?.waveform;
+    _currentTranscriptionId = autoStopId;
+    if (config.soundEnabled) audioController.playEnd();
+    if (_recordTimer) clearInterval(_recordTimer);
+    _isTranscribing = true;
+    updateReactState();
+  });
+
+  const unResult = listen<AutoTranscriptionPayload>('auto-transcription', async (event) => {
+    if (_currentTranscriptionId !== autoStopId) return;
+    const { text, transcript, error } = event.payload;
+    const config = getConfig();
+    const useGpu = config.useLocalGPU || false;
+
+    try {
+      if (error) {
+        if (error.includes('SILENCE_DETECTED')) {
+          getShowToast()('Skipped: Silence Detected');
+        } else {
+          getAddItem()({ timestamp: Date.now(), text: error, duration: autoStopDuration, error: true });
+        }
+        return;
+      }
+
+      getAddItem()({
+        timestamp: Date.now(),
+        text: text || '',
+        duration: autoStopDuration,
+        error: false,
+        backend: useGpu ? 'Local (GPU)' : 'Local (CPU)',
+        processingTime: transcript?.processing_ms ?? Date.now() - autoStopId - autoStopDuration,
+        waveform: autoStopWaveform,
+        language: transcript?.language.code,
+        transcript: transcript ?? undefined,
+      });
+      await deliverText(text || '');
+    } finally {
+      _isTranscribing = false;
+      updateReactState();
+    }
+  });
+
+  const unWarning = listen<{ remaining_secs: number; max_secs: number }>('recording-limit-warning', (event) => {
+    if (!_isRecording) return;
+    getShowToast()(`Recording stops in ${event.payload.remaining_secs}s`);
+  });
+
+  // Capture already stopped in the backend; finish the take the normal way
+  const unLimit = listen<number>('recording-limit-reached', (event) => {
+    if (!_isRecording) return;
+    getShowToast()(`Recording limit of ${Math.round(event.payload / 60)} min reached`);
+    recordingController.stopRecord();
+  });
+
+  // Backend leaves the lowest-priority terms out of the prompt; say so once per session
+  let vocabularyWarned = false;
+  const unVocabulary = listen<{ tokens: number; max_tokens: number; dropped: string[] }>('vocabulary-truncated', (event) => {
+    if (vocabularyWarned) return;
+    vocabularyWarned = true;
+    const { tokens, max_tokens, dropped } = event.payload;
+    getShowToast()(`Vocabulary too long (${tokens}/${max_tokens} tokens), left out ${dropped.length} terms: ${dropped.join(', ')}`);
+  });
+
+  return () => {
+    unStopped.then((fn) => fn());
+    unResult.then((fn) => fn());
+    unWarning.then((fn) => fn());
+    unLimit.then((fn) => fn());
+    unVocabulary.then((fn) => fn());
+  };
+};
+
 // Stable function references for shortcut manager
 export const toggleRecord = () => recordingController.toggleRecord();
 export const startRecord = () => recordingController.startRecord();
@@ -13,6 +13,8 @@ interface RecordingState {
   isCancelled: boolean;
   isGeneratingSrt: boolean;
   startTime: number;
+  isPaused: boolean;
+  pausedMs: number; // Total paused time in the current take
   recordTimer: number | null;
   srtProgress: SrtProgress | null;
   resultMessage: string | null;
@@ -35,6 +37,8 @@ const initialState = {
   isCancelled: false,
   isGeneratingSrt: false,
   startTime: 0,
+  isPaused: false,
+  pausedMs: 0,
   recordTimer: null,
   srtProgress: null,
   resultMessage: null,
@@ -11,6 +11,24 @@ export interface AppConfig {
   globalShortcut?: string;
   alwaysOnTop?: boolean;
   localTranslate?: boolean;
+  inputDeviceId?: string; // From list_input_devices, undefined = system default
+  autoStopOnSilence?: boolean;
+  autoStopHangMs?: number; // Trailing silence before auto-stop
+  warmMic?: boolean; // Keep the input stream open for pre-roll
+  preRollMs?: number;
+  maxRecordingMinutes?: number; // 0 = only the model/memory cap
+  liveTranscription?: boolean; // Partial results in the overlay while recording
+  highPassFilter?: boolean; // Preprocessing stages before Whisper
+  denoise?: boolean;
+  normalizeLoudness?: boolean;
+  loopbackDeviceId?: string; // System audio recorded alongside the mic
+  loopbackMode?: 'mix' | 'separate'; // One track, or "me" and "them"
+  language?: string; // Whisper code, 'auto' = detect
+  allowedLanguages?: string[]; // Detect only among these codes, empty = any
+  decodingProfile?: 'standard' | 'fast' | 'balanced' | 'accurate'; // Speed vs accuracy preset
+  spokenCommands?: boolean; // "comma", "new line", "scratch that" in dictation
+  keepRecordings?: boolean; // Keep the takes SRT files are made from
+  vocabularyProfile?: string; // Vocabulary lists of this profile are used besides the shared ones
 }
 
 export const DEFAULT_CONFIG: AppConfig = {
@@ -26,6 +44,22 @@ export const DEFAULT_CONFIG: AppConfig = {
   useLocalGPU: false,
   globalShortcut: 'Ctrl+Alt+Space',
   localTranslate: false,
+  autoStopOnSilence: false,
+  autoStopHangMs: 1500,
+  warmMic: false,
+  preRollMs: 300,
+  maxRecordingMinutes: 0,
+  liveTranscription: false,
+  highPassFilter: false,
+  denoise: false,
+  normalizeLoudness: false,
+  loopbackMode: 'mix',
+  language: 'auto',
+  allowedLanguages: [],
+  decodingProfile: 'standard',
+  spokenCommands: false,
+  keepRecordings: true,
+  vocabularyProfile: '',
 };
 
 export interface HistoryItem {
@@ -37,4 +71,97 @@ export interface HistoryItem {
   processingTime?: number; // Transcription time in ms
   isSrt?: boolean; // True if this is an SRT file entry
   srtPath?: string; // Path to the SRT file
+  recordingPath?: string; // Recorded take the SRT was made from, deleted with the item
+  waveform?: number[]; // Peak envelope of the recording, 0-1
+  language?: string; // Code the audio was transcribed as
+  transcript?: Transcript; // Segment timings and confidences, when the backend returned them
+}
+
+// Language a transcription was decoded in; probability is null when it was fixed in settings
+export interface DetectedLanguage {
+  code: string;
+  probability: number | null;
+}
+
+export interface TranscriptWord {
+  start_ms: number;
+  end_ms: number;
+  text: string;
+  probability: number;
+}
+
+export interface TranscriptSegment {
+  start_ms: number;
+  end_ms: number;
+  text: string;
+  avg_logprob: number;
+  no_speech_prob: number | null;
+  speaker: string | null; // 'Me' / 'Them' for separate-speaker takes
+  words: TranscriptWord[]; // Only with token timestamps (SRT)
+}
+
+// A segment the hallucination filter removed
+export interface FilteredSegment {
+  start_ms: number;
+  end_ms: number;
+  text: string;
+  reason: 'annotation' | 'known_phrase' | 'no_speech';
+}
+
+// Returned by transcribe_local / transcribe_recording, and with SRT results
+export interface Transcript {
+  text: string;
+  segments: TranscriptSegment[];
+  filtered: FilteredSegment[];
+  language: DetectedLanguage;
+  model: string;
+  backend: string;
+  processing_ms: number;
+}
+
+export interface SrtResult {
+  path: string;
+  transcript: Transcript;
+  archive_path: string | null; // Set when a recorded take was kept
+}
+
+export interface MediaStream {
+  index: number; // Position in the container, as ffmpeg numbers streams
+  kind: 'audio' | 'video' | 'subtitle' | 'other';
+  codec: string | null;
+  sample_rate: number | null;
+  channels: number | null;
+  duration_ms: number | null;
+  language: string | null; // Track language tag, if the container has one
+  language_code: string | null; // Whisper code for it, used when the language is left to detection
+}
+
+// Returned by probe_media
+export interface MediaInfo {
+  container: string; // Detected from content: 'wav', 'mp3', 'mp4', 'matroska', ... or 'unknown'
+  duration_ms: number | null;
+  sample_rate: number | null; // Of the audio stream that gets transcribed
+  channels: number | null;
+  has_video: boolean;
+  streams: MediaStream[];
+  decoder: 'wav' | 'native' | 'ffmpeg';
+}
+
+export interface CaptureDevice {
+  name: string;
+  sample_rate: number; // Native rate before resampling to 16kHz
+  channels: number;
+}
+
+// Returned by stop_recording
+export interface RecordingResult {
+  path: string | null; // Archive WAV, if saved
+  duration_ms: number; // Excludes paused time
+  pauses: { start_ms: number; end_ms: number }[];
+  device: CaptureDevice | null;
+  system_device: CaptureDevice | null;
+  peak: number;
+  rms: number;
+  speech_ratio: number; // 0-1, share of frames with speech
+  waveform: number[];
 }